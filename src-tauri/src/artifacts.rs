@@ -0,0 +1,325 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::process::Command;
+
+use crate::commands::App;
+
+static HOST: Lazy<HostInfo> = Lazy::new(detect_host);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    #[serde(rename = "arm64", alias = "aarch64")]
+    Arm64,
+    #[serde(rename = "x86_64", alias = "x64", alias = "intel")]
+    X86_64,
+    #[serde(rename = "universal")]
+    Universal,
+}
+
+impl std::fmt::Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Arch::Arm64 => "arm64",
+            Arch::X86_64 => "x86_64",
+            Arch::Universal => "universal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// One downloadable build of an app
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Artifact {
+    pub arch: Arch,
+    pub url: String,
+    #[serde(rename = "minOsVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_os_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Other hosts serving the same file, tried in order when `url` fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+}
+
+impl Artifact {
+    // The primary URL followed by its mirrors, without duplicates
+    pub fn urls(&self) -> Vec<&str> {
+        let mut urls: Vec<&str> = vec![];
+        for url in std::iter::once(&self.url).chain(self.mirrors.iter()) {
+            if !url.is_empty() && !urls.contains(&url.as_str()) {
+                urls.push(url);
+            }
+        }
+        urls
+    }
+}
+
+// An entry in an app's release history
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Release {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(rename = "releaseNotes")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    pub arch: Arch,
+    pub os_version: Option<String>,
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn detect_host() -> HostInfo {
+    // An x86_64 build running under Rosetta still sits on Apple silicon
+    let arch = if std::env::consts::ARCH == "aarch64"
+        || command_output("sysctl", &["-n", "hw.optional.arm64"]).as_deref() == Some("1")
+    {
+        Arch::Arm64
+    } else {
+        Arch::X86_64
+    };
+
+    HostInfo {
+        arch,
+        os_version: command_output("sw_vers", &["-productVersion"]),
+    }
+}
+
+pub fn host() -> &'static HostInfo {
+    &HOST
+}
+
+// Compare dotted versions numerically, treating missing parts as zero
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| {
+                part.chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse()
+                    .unwrap_or(0)
+            })
+            .collect()
+    };
+    let (a, b) = (parse(a), parse(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+// Lower is better; None means the build cannot run on this host at all
+fn arch_rank(artifact: Arch, host: Arch) -> Option<u8> {
+    match (artifact, host) {
+        (a, h) if a == h => Some(0),
+        (Arch::Universal, _) => Some(1),
+        // Intel builds still run on Apple silicon through Rosetta
+        (Arch::X86_64, Arch::Arm64) => Some(2),
+        _ => None,
+    }
+}
+
+// Pick the best artifact for the host: native over universal over Rosetta,
+// then the most specific minimum OS version that is still satisfied
+pub fn select_artifact(artifacts: &[Artifact], host: &HostInfo) -> Result<Artifact, String> {
+    let mut best: Option<(u8, &Artifact)> = None;
+    let mut reasons = vec![];
+
+    for artifact in artifacts {
+        let Some(rank) = arch_rank(artifact.arch, host.arch) else {
+            reasons.push(format!(
+                "{} build does not run on {}",
+                artifact.arch, host.arch
+            ));
+            continue;
+        };
+
+        if let (Some(required), Some(current)) = (&artifact.min_os_version, &host.os_version) {
+            if compare_versions(current, required) == Ordering::Less {
+                reasons.push(format!(
+                    "{} build requires macOS {} or later (this Mac has {})",
+                    artifact.arch, required, current
+                ));
+                continue;
+            }
+        }
+
+        let better = match best {
+            None => true,
+            Some((best_rank, best_artifact)) => {
+                rank < best_rank
+                    || (rank == best_rank
+                        && compare_versions(
+                            artifact.min_os_version.as_deref().unwrap_or("0"),
+                            best_artifact.min_os_version.as_deref().unwrap_or("0"),
+                        ) == Ordering::Greater)
+            }
+        };
+        if better {
+            best = Some((rank, artifact));
+        }
+    }
+
+    match best {
+        Some((_, artifact)) => Ok(artifact.clone()),
+        None if reasons.is_empty() => Err("No downloads are available for this app".to_string()),
+        None => Err(reasons.join("; ")),
+    }
+}
+
+// Manifests without an artifact list only have the legacy single download URL
+pub fn app_artifacts(app: &App) -> Vec<Artifact> {
+    if !app.artifacts.is_empty() {
+        return app.artifacts.clone();
+    }
+    if app.download_url.is_empty() {
+        return vec![];
+    }
+    vec![Artifact {
+        arch: Arch::Universal,
+        url: app.download_url.clone(),
+        min_os_version: None,
+        size: app.size,
+        sha256: app.sha256.clone(),
+        mirrors: app.mirrors.clone(),
+    }]
+}
+
+// Artifacts of any listed version, including the current one
+pub fn release_artifacts(app: &App, version: &str) -> Result<Vec<Artifact>, String> {
+    if let Some(release) = app.releases.iter().find(|r| r.version == version) {
+        return Ok(release.artifacts.clone());
+    }
+    if app.version == version {
+        return Ok(app_artifacts(app));
+    }
+    Err(format!("Version {} of {} is not listed", version, app.id))
+}
+
+// The listed artifact for a download or mirror URL, so its size and hash can
+// be checked and its other mirrors tried
+pub fn find_artifact(app: &App, url: &str) -> Option<Artifact> {
+    app_artifacts(app)
+        .into_iter()
+        .chain(app.releases.iter().flat_map(|r| r.artifacts.clone()))
+        .find(|artifact| artifact.urls().contains(&url))
+}
+
+// Fill in the artifact chosen for this Mac, or why none of them fit
+pub fn apply_host_compatibility(app: &mut App) {
+    match select_artifact(&app_artifacts(app), host()) {
+        Ok(artifact) => {
+            app.download_url = artifact.url.clone();
+            app.selected_artifact = Some(artifact);
+            app.incompatible_reason = None;
+        }
+        Err(reason) => {
+            app.selected_artifact = None;
+            app.incompatible_reason = Some(reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(arch: Arch, min_os_version: Option<&str>) -> Artifact {
+        Artifact {
+            arch,
+            url: format!(
+                "https://example.com/{}-{}.dmg",
+                arch,
+                min_os_version.unwrap_or("any")
+            ),
+            min_os_version: min_os_version.map(str::to_string),
+            size: None,
+            sha256: None,
+            mirrors: vec![],
+        }
+    }
+
+    fn host(arch: Arch, os_version: &str) -> HostInfo {
+        HostInfo {
+            arch,
+            os_version: Some(os_version.to_string()),
+        }
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(compare_versions("10.10", "10.9"), Ordering::Greater);
+        assert_eq!(compare_versions("14", "14.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("v1.2.3", "1.2.4"), Ordering::Less);
+        assert_eq!(compare_versions("2.0-beta", "2.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn prefers_native_over_universal_over_rosetta() {
+        let host = host(Arch::Arm64, "14.0");
+        let intel = artifact(Arch::X86_64, None);
+        let universal = artifact(Arch::Universal, None);
+        let native = artifact(Arch::Arm64, None);
+
+        let all = [intel.clone(), universal.clone(), native.clone()];
+        assert_eq!(select_artifact(&all, &host).unwrap().arch, Arch::Arm64);
+        let no_native = [intel.clone(), universal];
+        assert_eq!(
+            select_artifact(&no_native, &host).unwrap().arch,
+            Arch::Universal
+        );
+        assert_eq!(select_artifact(&[intel], &host).unwrap().arch, Arch::X86_64);
+    }
+
+    #[test]
+    fn prefers_most_specific_satisfied_min_os_version() {
+        let artifacts = [
+            artifact(Arch::Arm64, None),
+            artifact(Arch::Arm64, Some("12.0")),
+            artifact(Arch::Arm64, Some("13.5")),
+            artifact(Arch::Arm64, Some("15.0")),
+        ];
+        let selected = select_artifact(&artifacts, &host(Arch::Arm64, "14.2")).unwrap();
+        assert_eq!(selected.min_os_version.as_deref(), Some("13.5"));
+    }
+
+    #[test]
+    fn explains_why_nothing_fits() {
+        let artifacts = [
+            artifact(Arch::Arm64, None),
+            artifact(Arch::X86_64, Some("13.0")),
+        ];
+        let error = select_artifact(&artifacts, &host(Arch::X86_64, "12.6")).unwrap_err();
+        assert_eq!(
+            error,
+            "arm64 build does not run on x86_64; \
+             x86_64 build requires macOS 13.0 or later (this Mac has 12.6)"
+        );
+        assert_eq!(
+            select_artifact(&[], &host(Arch::Arm64, "14.0")).unwrap_err(),
+            "No downloads are available for this app"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::artifacts;
+use crate::commands::App;
+use crate::fetcher::RegistryFetcher;
+use crate::registry::{self, ManifestFailure};
+use crate::registry_cache::unix_now;
+use crate::settings;
+
+const SNAPSHOT_FILE_NAME: &str = "catalog.json";
+
+// Most recently loaded catalog and when it finished loading, reused by
+// commands that only need to look apps up
+static LAST_CATALOG: Lazy<Mutex<Option<(Instant, Catalog)>>> = Lazy::new(|| Mutex::new(None));
+
+// Held while a load runs, so concurrent callers wait for it instead of
+// fetching every source and writing the snapshot a second time
+static LOADING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Catalog {
+    pub apps: Vec<App>,
+    // True when at least one source was served from the offline snapshot
+    pub stale: bool,
+    // Unix timestamp (seconds) of the oldest data included in this catalog
+    pub fetched_at: u64,
+    pub stale_sources: Vec<String>,
+    // Manifests that were listed in an index but could not be loaded
+    pub failures: Vec<ManifestFailure>,
+    // Sources that could not be loaded or verified at all
+    pub source_errors: Vec<SourceError>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceError {
+    pub source: String,
+    pub error: String,
+}
+
+// Last successful result for every source, persisted so the store works offline
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct CatalogSnapshot {
+    sources: HashMap<String, SourceSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SourceSnapshot {
+    fetched_at: u64,
+    apps: Vec<App>,
+}
+
+fn load_snapshot() -> CatalogSnapshot {
+    let path = match settings::get_app_data_directory() {
+        Ok(dir) => dir.join(SNAPSHOT_FILE_NAME),
+        Err(_) => return CatalogSnapshot::default(),
+    };
+    fs::read(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_snapshot(snapshot: &CatalogSnapshot) -> Result<(), String> {
+    let path = settings::get_app_data_directory()?.join(SNAPSHOT_FILE_NAME);
+    let contents = serde_json::to_vec(snapshot)
+        .map_err(|e| format!("Failed to serialize catalog snapshot: {}", e))?;
+    settings::write_atomic(&path, &contents)
+        .map_err(|e| format!("Failed to write catalog snapshot: {}", e))
+}
+
+// The catalog loaded by the last fetch_apps call, loading it first if needed
+pub async fn current_catalog(client: &reqwest::Client) -> Result<Catalog, String> {
+    if let Some((_, catalog)) = LAST_CATALOG.lock().unwrap().clone() {
+        return Ok(catalog);
+    }
+    load_catalog(client).await
+}
+
+// Reload the catalog. A load that was already running when this was called
+// is waited for and its result shared instead of starting another one.
+pub async fn load_catalog(client: &reqwest::Client) -> Result<Catalog, String> {
+    let requested_at = Instant::now();
+    let _loading = LOADING.lock().await;
+    let last = LAST_CATALOG.lock().unwrap().clone();
+    if let Some((loaded_at, catalog)) = last {
+        if loaded_at >= requested_at {
+            return Ok(catalog);
+        }
+    }
+
+    let catalog = fetch_catalog(client).await?;
+    *LAST_CATALOG.lock().unwrap() = Some((Instant::now(), catalog.clone()));
+    Ok(catalog)
+}
+
+// Fetch all enabled sources, falling back to the last good snapshot for any
+// source that cannot be reached
+async fn fetch_catalog(client: &reqwest::Client) -> Result<Catalog, String> {
+    let settings = settings::current_settings();
+    let sources = registry::ordered_sources(&settings.registries);
+    let fetcher = RegistryFetcher::new(client.clone(), settings.registry_fetch);
+
+    if sources.is_empty() {
+        return Err("No app registries are enabled".to_string());
+    }
+
+    // Fetch every source in parallel, keeping them in priority order
+    let results = futures_util::future::join_all(
+        sources
+            .iter()
+            .map(|source| registry::fetch_source(&fetcher, source)),
+    )
+    .await;
+
+    let mut snapshot = load_snapshot();
+    let mut snapshot_changed = false;
+    let now = unix_now();
+    let mut fetched_at = now;
+    let mut source_apps = vec![];
+    let mut stale_sources = vec![];
+    let mut errors = vec![];
+    let mut failures = vec![];
+
+    for (source, result) in sources.iter().zip(results) {
+        match result {
+            Ok(result) => {
+                snapshot.sources.insert(
+                    source.id.clone(),
+                    SourceSnapshot {
+                        fetched_at: now,
+                        apps: result.apps.clone(),
+                    },
+                );
+                snapshot_changed = true;
+                source_apps.push(result.apps);
+                failures.extend(result.failures);
+            }
+            Err(e) => {
+                eprintln!("Registry {} failed: {}", source.id, e);
+                errors.push(SourceError {
+                    source: source.id.clone(),
+                    error: e,
+                });
+
+                if let Some(cached) = snapshot.sources.get(&source.id) {
+                    eprintln!(
+                        "Serving registry {} from snapshot taken at {}",
+                        source.id, cached.fetched_at
+                    );
+                    fetched_at = fetched_at.min(cached.fetched_at);
+                    stale_sources.push(source.id.clone());
+                    source_apps.push(cached.apps.clone());
+                }
+            }
+        }
+    }
+
+    if snapshot_changed {
+        if let Err(e) = save_snapshot(&snapshot) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    let mut apps = registry::merge_sources(source_apps);
+    for app in &mut apps {
+        artifacts::apply_host_compatibility(app);
+    }
+
+    if apps.is_empty() {
+        if !errors.is_empty() {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|e| format!("{}: {}", e.source, e.error))
+                .collect();
+            return Err(messages.join("; "));
+        }
+        return Err("Failed to fetch any apps from registry".to_string());
+    }
+
+    Ok(Catalog {
+        apps,
+        stale: !stale_sources.is_empty(),
+        fetched_at,
+        stale_sources,
+        failures,
+        source_errors: errors,
+    })
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::catalog;
+use crate::fetcher::RegistryFetcher;
+use crate::registry::{self, RegistrySource};
+use crate::settings;
+
+const CATEGORIES_PATH: &str = "categories.json";
+
+#[derive(Debug, Deserialize)]
+struct CategoriesConfig {
+    #[serde(default)]
+    categories: Vec<CategoryMetadata>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct CategoryMetadata {
+    id: String,
+    name: String,
+    #[serde(default)]
+    icon: String,
+    // Other spellings that should be counted under this category
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+    pub app_count: usize,
+    // Number of apps in this category for every registry source
+    pub source_counts: BTreeMap<String, usize>,
+    // Ids of the counted apps, so the UI lists exactly those
+    pub app_ids: Vec<String>,
+}
+
+// "  Developer   Tools " and "developer tools" both become "developer-tools"
+pub fn normalize_category(category: &str) -> String {
+    category
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn display_name(category: &str) -> String {
+    category.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+async fn fetch_metadata(
+    fetcher: &RegistryFetcher,
+    source: &RegistrySource,
+) -> Result<Vec<CategoryMetadata>, String> {
+    match registry::fetch_source_file(fetcher, source, CATEGORIES_PATH).await {
+        Ok(bytes) => registry::parse_json::<CategoriesConfig>(&bytes)
+            .map(|config| config.categories)
+            .map_err(|e| format!("Failed to parse categories: {}", e)),
+        // Category metadata is optional for a registry
+        Err(e) if e.not_found => Ok(vec![]),
+        Err(e) => Err(format!("Failed to fetch categories: {}", e)),
+    }
+}
+
+pub async fn list_categories(client: &reqwest::Client) -> Result<Vec<Category>, String> {
+    let catalog = catalog::current_catalog(client).await?;
+
+    let settings = settings::current_settings();
+    let sources = registry::ordered_sources(&settings.registries);
+    let fetcher = RegistryFetcher::new(client.clone(), settings.registry_fetch);
+
+    let results = futures_util::future::join_all(
+        sources
+            .iter()
+            .map(|source| fetch_metadata(&fetcher, source)),
+    )
+    .await;
+
+    // Metadata from higher priority sources wins; aliases map onto the canonical id
+    let mut metadata: HashMap<String, CategoryMetadata> = HashMap::new();
+    let mut aliases: HashMap<String, String> = HashMap::new();
+    for (source, result) in sources.iter().zip(results) {
+        let entries = result.unwrap_or_else(|e| {
+            eprintln!("Registry {}: {}", source.id, e);
+            vec![]
+        });
+        for entry in entries {
+            let id = normalize_category(&entry.id);
+            if metadata.contains_key(&id) {
+                continue;
+            }
+            for alias in &entry.aliases {
+                aliases
+                    .entry(normalize_category(alias))
+                    .or_insert_with(|| id.clone());
+            }
+            metadata.insert(id, entry);
+        }
+    }
+
+    let mut categories: HashMap<String, Category> = HashMap::new();
+    for app in &catalog.apps {
+        let normalized = normalize_category(&app.category);
+        if normalized.is_empty() {
+            continue;
+        }
+        let id = aliases.get(&normalized).cloned().unwrap_or(normalized);
+
+        let category = categories.entry(id.clone()).or_insert_with(|| {
+            let meta = metadata.get(&id);
+            Category {
+                id: id.clone(),
+                name: meta
+                    .map(|m| m.name.clone())
+                    .unwrap_or_else(|| display_name(&app.category)),
+                icon: meta.map(|m| m.icon.clone()).unwrap_or_default(),
+                app_count: 0,
+                source_counts: BTreeMap::new(),
+                app_ids: vec![],
+            }
+        });
+
+        category.app_count += 1;
+        category.app_ids.push(app.id.clone());
+        let source = app.source.clone().unwrap_or_default();
+        *category.source_counts.entry(source).or_insert(0) += 1;
+    }
+
+    let mut categories: Vec<Category> = categories.into_values().collect();
+    categories.sort_by(|a, b| {
+        b.app_count
+            .cmp(&a.app_count)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    Ok(categories)
+}
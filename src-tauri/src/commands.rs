@@ -1,16 +1,20 @@
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
 use std::os::unix::fs as unix_fs;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
 use tauri::Emitter;
 
-// Global map to store cancellation flags for each download
-static DOWNLOAD_CANCELLATIONS: Lazy<Mutex<HashMap<String, bool>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+use crate::artifacts::{self, Artifact, Release};
+use crate::catalog::{self, Catalog, SourceError};
+use crate::categories::{self, Category};
+use crate::curated::{self, CuratedSection};
+use crate::formats::{self, ArtifactFormat};
+use crate::http::{self, HttpClient};
+use crate::queue::{self, QueueSnapshot};
+use crate::registry;
+use crate::registry_cache;
+use crate::settings::{self, Settings};
+use crate::throttle;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct App {
@@ -20,8 +24,17 @@ pub struct App {
     pub version: String,
     pub category: String,
     pub icon: String,
-    #[serde(rename = "downloadUrl")]
+    // Legacy single download; `artifacts` takes precedence when present
+    #[serde(rename = "downloadUrl", default)]
     pub download_url: String,
+    // Expected size and hash of the legacy download
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Fallback hosts for the legacy download
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
     pub homepage: String,
     pub license: String,
     pub author: String,
@@ -32,6 +45,31 @@ pub struct App {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "hasUpdate")]
     pub has_update: Option<bool>,
+    // Id of the registry this entry was loaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // Builds per architecture and minimum macOS version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<Artifact>,
+    // Release history, newest first, so older versions can be reinstalled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub releases: Vec<Release>,
+    // Computed for this Mac when the catalog is loaded
+    #[serde(rename = "selectedArtifact")]
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub selected_artifact: Option<Artifact>,
+    #[serde(rename = "incompatibleReason")]
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub incompatible_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    Starting,
+    Downloading,
+    Verifying,
+    Paused,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -40,7 +78,10 @@ pub struct DownloadProgress {
     pub progress: f64,
     pub downloaded: u64,
     pub total: u64,
-    pub status: String,
+    pub phase: DownloadPhase,
+    // Smoothed transfer speed; zero outside the downloading phase
+    pub bytes_per_second: f64,
+    pub eta_seconds: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -49,6 +90,24 @@ pub struct DownloadComplete {
     pub file_path: String,
     pub success: bool,
     pub error: Option<String>,
+    pub error_kind: Option<DownloadErrorKind>,
+    // Mirror that served the file
+    pub source_url: Option<String>,
+}
+
+// Whether a failed download is worth trying again
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadErrorKind {
+    // Timeouts, dropped connections and server errors that outlasted the retries
+    Retryable,
+    // 4xx responses and local file errors
+    Fatal,
+    // The file did not match the size or sha256 listed in the manifest
+    ChecksumMismatch,
+    // The server stopped sending or stayed below the minimum speed, even after retries
+    Stalled,
+    Cancelled,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -87,192 +146,178 @@ fn get_apps_file_path() -> PathBuf {
     PathBuf::from("apps.json")
 }
 
-// Configuration for apps registry URL
-const APPS_REGISTRY_BASE_URL: &str =
-    "https://raw.githubusercontent.com/Nandanrmenon/fossintosh-repo/main";
-const APPS_REGISTRY_INDEX_URL: &str =
-    "https://raw.githubusercontent.com/Nandanrmenon/fossintosh-repo/main/index.json";
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AppRegistry {
-    pub id: String,
-    pub url: String,
+// Command to fetch apps from all configured registries. Falls back to the
+// last good snapshot when a registry is unreachable and flags the result as stale.
+#[tauri::command]
+pub async fn fetch_apps(client: tauri::State<'_, HttpClient>) -> Result<Catalog, String> {
+    // Saved network settings that could not be applied explain failed requests
+    let network_error = client.error();
+    let mut catalog =
+        catalog::load_catalog(&client.get())
+            .await
+            .map_err(|e| match &network_error {
+                Some(network_error) => format!("{} (network settings: {})", e, network_error),
+                None => e,
+            })?;
+    if let Some(error) = network_error {
+        catalog.source_errors.push(SourceError {
+            source: "network settings".to_string(),
+            error,
+        });
+    }
+    Ok(catalog)
 }
 
-// Command to fetch apps from your registry
+// Command to fetch the curated Discover sections that are currently scheduled
 #[tauri::command]
-pub async fn fetch_apps() -> Result<Vec<App>, String> {
-    let client = reqwest::Client::new();
+pub async fn fetch_curated(
+    client: tauri::State<'_, HttpClient>,
+) -> Result<Vec<CuratedSection>, String> {
+    curated::load_curated(&client.get()).await
+}
 
-    // Fetch the index (list of app IDs)
-    let app_ids: Vec<String> = client
-        .get(APPS_REGISTRY_INDEX_URL)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch registry index: {}", e))?
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse registry index: {}", e))?;
-
-    // Fetch each app in parallel
-    let mut app_futures = vec![];
-    for app_id in app_ids {
-        let client = client.clone();
-        let url = format!("{}/apps/{}.json", APPS_REGISTRY_BASE_URL, app_id);
-        let future = async move { client.get(&url).send().await.ok()?.json::<App>().await.ok() };
-        app_futures.push(future);
-    }
+// Command to list normalized categories with app counts
+#[tauri::command]
+pub async fn list_categories(
+    client: tauri::State<'_, HttpClient>,
+) -> Result<Vec<Category>, String> {
+    categories::list_categories(&client.get()).await
+}
 
-    let apps: Vec<App> = futures_util::future::join_all(app_futures)
-        .await
-        .into_iter()
-        .filter_map(|app| app)
-        .collect();
+// Command to read the current settings
+#[tauri::command]
+pub async fn get_settings() -> Result<Settings, String> {
+    Ok(settings::current_settings())
+}
 
-    if apps.is_empty() {
-        return Err("Failed to fetch any apps from registry".to_string());
-    }
+// Command to replace the settings, e.g. after adding or reordering registries
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    client: tauri::State<'_, HttpClient>,
+) -> Result<Settings, String> {
+    registry::validate_sources(&settings.registries)?;
+    settings.downloads.validate()?;
+    // Build the client first so a bad proxy or CA bundle is rejected, not saved
+    let new_client = http::build_client(&settings)?;
+    let settings = settings::save_settings(settings)?;
+    client.replace(new_client);
+    // New bandwidth limits apply to running downloads from the next chunk
+    throttle::configure(&settings.downloads);
+    // A higher download limit may let waiting downloads start
+    queue::pump();
+    Ok(settings)
+}
 
-    Ok(apps)
+// Command to drop all cached registry responses
+#[tauri::command]
+pub async fn clear_registry_cache() -> Result<String, String> {
+    registry_cache::clear()?;
+    registry::forget_missing_indexes();
+    Ok("Registry cache cleared".to_string())
 }
 
-// Command to download an app
+// Command to queue an app download
 #[tauri::command]
 pub async fn download_app(
     app_id: String,
     download_url: String,
-    window: tauri::Window,
+    priority: Option<i32>,
+    client: tauri::State<'_, HttpClient>,
 ) -> Result<String, String> {
-    let downloads_dir = get_downloads_directory()
-        .map_err(|e| format!("Failed to get downloads directory: {}", e))?;
-
-    // Create file name from app_id
-    let file_name = format!("{}.dmg", app_id);
-    let file_path = downloads_dir.join(&file_name);
-
-    // Emit start event
-    let _ = window.emit(
-        "download_progress",
-        DownloadProgress {
-            app_id: app_id.clone(),
-            progress: 0.0,
-            downloaded: 0,
-            total: 0,
-            status: "Starting download...".to_string(),
+    // Look up the manifest entry so the download can be verified
+    let listed = catalog::current_catalog(&client.get())
+        .await
+        .ok()
+        .and_then(|catalog| catalog.apps.into_iter().find(|app| app.id == app_id));
+
+    let artifact = match listed {
+        // Only download what the manifest lists and what runs on this Mac
+        Some(app) => {
+            let cannot = |reason: String| format!("{} cannot be downloaded: {}", app.name, reason);
+            let Some(selected) = &app.selected_artifact else {
+                return Err(cannot(app.incompatible_reason.clone().unwrap_or_else(
+                    || "No downloads are available for this app".to_string(),
+                )));
+            };
+            match artifacts::find_artifact(&app, &download_url).filter(|_| !download_url.is_empty())
+            {
+                // The UI asked for a specific listed build; check it fits too
+                Some(found) => {
+                    artifacts::select_artifact(&[found], artifacts::host()).map_err(cannot)?
+                }
+                None => selected.clone(),
+            }
+        }
+        // The catalog could not be loaded; trust the URL the UI was showing
+        None if !download_url.is_empty() => Artifact {
+            arch: artifacts::host().arch,
+            url: download_url.clone(),
+            min_os_version: None,
+            size: None,
+            sha256: None,
+            mirrors: vec![],
         },
-    );
+        None => return Err(format!("No download URL for app: {}", app_id)),
+    };
 
-    // Create HTTP client
-    let client = reqwest::Client::new();
-
-    match client.get(&download_url).send().await {
-        Ok(response) => {
-            let total_size = response.content_length().unwrap_or(0);
-
-            // Create file
-            let file = fs::File::create(&file_path)
-                .map_err(|e| format!("Failed to create file: {}", e))?;
-
-            let mut stream = response.bytes_stream();
-            use futures_util::stream::StreamExt;
-
-            let mut downloaded: u64 = 0;
-            let mut file_writer = std::io::BufWriter::new(file);
-
-            while let Some(chunk) = stream.next().await {
-                // Check if download was cancelled
-                {
-                    let cancellations = DOWNLOAD_CANCELLATIONS.lock().unwrap();
-                    if cancellations.get(&app_id).copied().unwrap_or(false) {
-                        drop(cancellations);
-                        // Clean up cancellation flag
-                        let mut cancel_write = DOWNLOAD_CANCELLATIONS.lock().unwrap();
-                        cancel_write.remove(&app_id);
-                        // Delete partial file
-                        let _ = fs::remove_file(&file_path);
-                        let _ = window.emit(
-                            "download_complete",
-                            DownloadComplete {
-                                app_id: app_id.clone(),
-                                file_path: String::new(),
-                                success: false,
-                                error: Some("Download cancelled".to_string()),
-                            },
-                        );
-                        return Ok(format!("Download cancelled: {}", app_id));
-                    }
-                }
+    queue::enqueue(&app_id, artifact, priority.unwrap_or(0), false)?;
+    Ok(format!("Queued download: {}", app_id))
+}
 
-                let chunk = chunk.map_err(|e| format!("Download error: {}", e))?;
-                file_writer
-                    .write_all(&chunk)
-                    .map_err(|e| format!("Failed to write to file: {}", e))?;
-
-                downloaded += chunk.len() as u64;
-                let progress = if total_size > 0 {
-                    (downloaded as f64 / total_size as f64) * 100.0
-                } else {
-                    0.0
-                };
-
-                // Emit progress event
-                let _ = window.emit(
-                    "download_progress",
-                    DownloadProgress {
-                        app_id: app_id.clone(),
-                        progress,
-                        downloaded,
-                        total: total_size,
-                        status: format!(
-                            "Downloading: {:.1}%",
-                            (downloaded as f64 / total_size.max(1) as f64) * 100.0
-                        ),
-                    },
-                );
-            }
+// Command to list queued, active and recently completed download/install jobs,
+// including the ones restored after a restart
+#[tauri::command]
+pub async fn list_jobs() -> Result<QueueSnapshot, String> {
+    Ok(queue::snapshot())
+}
 
-            file_writer
-                .flush()
-                .map_err(|e| format!("Failed to flush file: {}", e))?;
-
-            // Emit completion event
-            let _ = window.emit(
-                "download_complete",
-                DownloadComplete {
-                    app_id: app_id.clone(),
-                    file_path: file_path.to_string_lossy().to_string(),
-                    success: true,
-                    error: None,
-                },
-            );
+// Command to change the priority of a queued download
+#[tauri::command]
+pub async fn set_download_priority(app_id: String, priority: i32) -> Result<QueueSnapshot, String> {
+    queue::set_priority(&app_id, priority)
+}
 
-            Ok(format!("Downloaded app to: {}", file_path.display()))
-        }
-        Err(e) => {
-            let error_msg = format!("Failed to download: {}", e);
-            let _ = window.emit(
-                "download_complete",
-                DownloadComplete {
-                    app_id: app_id.clone(),
-                    file_path: String::new(),
-                    success: false,
-                    error: Some(error_msg.clone()),
-                },
-            );
-            Err(error_msg)
-        }
-    }
+// Command to move a queued download to another position in the queue
+#[tauri::command]
+pub async fn move_download(app_id: String, position: usize) -> Result<QueueSnapshot, String> {
+    queue::move_job(&app_id, position)
 }
 
-fn get_downloads_directory() -> Result<PathBuf, String> {
-    let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
-    let downloads = PathBuf::from(home).join("Downloads");
+// Command to pause a running download, keeping what was downloaded so far
+#[tauri::command]
+pub async fn pause_download(app_id: String) -> Result<String, String> {
+    queue::pause(&app_id)?;
+    Ok(format!("Download paused for app: {}", app_id))
+}
 
-    // Create downloads directory if it doesn't exist
-    fs::create_dir_all(&downloads)
-        .map_err(|e| format!("Failed to create downloads directory: {}", e))?;
+// Command to put a paused download back in the queue
+#[tauri::command]
+pub async fn resume_download(app_id: String) -> Result<String, String> {
+    queue::resume(&app_id)?;
+    Ok(format!("Download resumed for app: {}", app_id))
+}
 
-    Ok(downloads)
+// Command to download and install a specific listed version of an app
+#[tauri::command]
+pub async fn install_version(
+    app_id: String,
+    version: String,
+    client: tauri::State<'_, HttpClient>,
+) -> Result<String, String> {
+    let catalog = catalog::current_catalog(&client.get()).await?;
+    let app = catalog
+        .apps
+        .iter()
+        .find(|app| app.id == app_id)
+        .ok_or_else(|| format!("Unknown app: {}", app_id))?;
+
+    let artifacts = artifacts::release_artifacts(app, &version)?;
+    let artifact = artifacts::select_artifact(&artifacts, artifacts::host())
+        .map_err(|e| format!("{} {} cannot be installed: {}", app.name, version, e))?;
+
+    queue::enqueue(&app_id, artifact, 0, true)?;
+    Ok(format!("Queued {} {} for installation", app.name, version))
 }
 
 // Command to check for updates
@@ -284,8 +329,11 @@ pub async fn check_updates(_installed_apps: Vec<String>) -> Result<Vec<String>,
 
 // Command to search apps
 #[tauri::command]
-pub async fn search_apps(query: String) -> Result<Vec<App>, String> {
-    let apps = fetch_apps().await?;
+pub async fn search_apps(
+    query: String,
+    client: tauri::State<'_, HttpClient>,
+) -> Result<Vec<App>, String> {
+    let apps = fetch_apps(client).await?.apps;
 
     let query_lower = query.to_lowercase();
     let results: Vec<App> = apps
@@ -303,8 +351,7 @@ pub async fn search_apps(query: String) -> Result<Vec<App>, String> {
 // Command to cancel a download
 #[tauri::command]
 pub async fn cancel_download(app_id: String) -> Result<String, String> {
-    let mut cancellations = DOWNLOAD_CANCELLATIONS.lock().unwrap();
-    cancellations.insert(app_id.clone(), true);
+    queue::cancel(&app_id)?;
     Ok(format!("Download cancelled for app: {}", app_id))
 }
 
@@ -313,8 +360,10 @@ pub async fn cancel_download(app_id: String) -> Result<String, String> {
 pub async fn install_app(
     app_id: String,
     file_path: String,
-    window: tauri::Window,
+    app: tauri::AppHandle,
 ) -> Result<String, String> {
+    registry::validate_app_id(&app_id)?;
+
     // Expand ~ to home directory
     let expanded_path = if file_path.starts_with("~") {
         let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
@@ -331,13 +380,11 @@ pub async fn install_app(
         ));
     }
 
-    let file_extension = std::path::Path::new(&expanded_path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    // Go by the file's content; the name only decides when that is unknown
+    let format = formats::detect(std::path::Path::new(&expanded_path))?
+        .or_else(|| ArtifactFormat::from_file_name(&expanded_path));
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.clone(),
@@ -346,21 +393,178 @@ pub async fn install_app(
         },
     );
 
-    match file_extension.as_str() {
-        "dmg" => install_dmg(&app_id, &expanded_path, &window).await,
-        "pkg" => install_pkg(&app_id, &expanded_path, &window).await,
-        _ => Err(format!("Unsupported file format: {}", file_extension)),
+    match format {
+        Some(ArtifactFormat::Dmg) => install_dmg(&app_id, &expanded_path, &app).await,
+        Some(ArtifactFormat::Pkg) => install_pkg(&app_id, &expanded_path, &app).await,
+        Some(format) => install_archive(&app_id, &expanded_path, format, &app).await,
+        None => Err(format!("Unsupported file format: {}", expanded_path)),
     }
 }
 
+// Look for an .app bundle at the top of `dir` or one folder down, which is
+// how zipped and tarred apps are usually laid out
+fn find_app_bundle(dir: &std::path::Path, depth: usize) -> Option<PathBuf> {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries
+        .iter()
+        .find(|path| path.extension().and_then(|ext| ext.to_str()) == Some("app"))
+        .cloned()
+        .or_else(|| {
+            if depth == 0 {
+                return None;
+            }
+            entries
+                .iter()
+                .filter(|path| path.is_dir())
+                .find_map(|path| find_app_bundle(path, depth - 1))
+        })
+}
+
+// A new, uniquely named directory under the temp dir. Creating it fails if the
+// name is taken, so nothing that already exists is reused or removed.
+fn create_extract_dir() -> Result<PathBuf, String> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let mut attempt = 0u32;
+    loop {
+        let extract_dir = std::env::temp_dir().join(format!(
+            "fossintosh-{}-{:08x}-{}",
+            std::process::id(),
+            nanos,
+            attempt
+        ));
+        match fs::create_dir(&extract_dir) {
+            Ok(()) => return Ok(extract_dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(e) => return Err(format!("Failed to create extraction directory: {}", e)),
+        }
+    }
+}
+
+async fn install_archive(
+    app_id: &str,
+    file_path: &str,
+    format: ArtifactFormat,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    use std::process::Command;
+
+    let _ = app.emit(
+        "install_progress",
+        InstallProgress {
+            app_id: app_id.to_string(),
+            progress: 20.0,
+            status: "Extracting archive...".to_string(),
+        },
+    );
+
+    let extract_dir = create_extract_dir()?;
+
+    // ditto keeps the extended attributes and symlinks app bundles rely on
+    let output = match format {
+        ArtifactFormat::Zip => Command::new("ditto")
+            .arg("-x")
+            .arg("-k")
+            .arg(file_path)
+            .arg(&extract_dir)
+            .output(),
+        _ => Command::new("tar")
+            .arg("-xf")
+            .arg(file_path)
+            .arg("-C")
+            .arg(&extract_dir)
+            .output(),
+    }
+    .map_err(|e| format!("Failed to extract archive: {}", e))?;
+
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&extract_dir);
+        return Err(format!(
+            "Failed to extract archive: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let _ = app.emit(
+        "install_progress",
+        InstallProgress {
+            app_id: app_id.to_string(),
+            progress: 50.0,
+            status: "Finding app bundle...".to_string(),
+        },
+    );
+
+    let result = match find_app_bundle(&extract_dir, 1) {
+        Some(app_bundle) => copy_app_bundle(app_id, &app_bundle, app),
+        None => Err("No .app bundle found in archive".to_string()),
+    };
+    let _ = fs::remove_dir_all(&extract_dir);
+    let app_name = result?;
+
+    let _ = app.emit(
+        "install_complete",
+        InstallComplete {
+            app_id: app_id.to_string(),
+            success: true,
+            error: None,
+        },
+    );
+
+    Ok(format!(
+        "Successfully installed {} to Applications",
+        app_name
+    ))
+}
+
+// Replace any existing copy of the bundle in Applications; returns its name
+fn copy_app_bundle(
+    app_id: &str,
+    app_bundle: &Path,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    let app_name = app_bundle
+        .file_name()
+        .ok_or("Invalid app name")?
+        .to_string_lossy()
+        .to_string();
+
+    let _ = app.emit(
+        "install_progress",
+        InstallProgress {
+            app_id: app_id.to_string(),
+            progress: 60.0,
+            status: format!("Copying {} to Applications...", app_name),
+        },
+    );
+
+    let applications_dir = get_applications_directory()?;
+    let destination = applications_dir.join(&app_name);
+
+    // Remove if already exists
+    if destination.exists() {
+        fs::remove_dir_all(&destination)
+            .map_err(|e| format!("Failed to remove existing app: {}", e))?;
+    }
+
+    copy_dir_all(app_bundle, &destination).map_err(|e| format!("Failed to copy app: {}", e))?;
+    Ok(app_name)
+}
+
 async fn install_dmg(
     app_id: &str,
     file_path: &str,
-    window: &tauri::Window,
+    app: &tauri::AppHandle,
 ) -> Result<String, String> {
     use std::process::Command;
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.to_string(),
@@ -436,7 +640,7 @@ async fn install_dmg(
 
     eprintln!("Extracted mount point: {:?}", mount_point);
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.to_string(),
@@ -461,34 +665,10 @@ async fn install_dmg(
         })
         .ok_or("No .app bundle found in DMG")?;
 
-    let app_name = app_bundle
-        .file_name()
-        .ok_or("Invalid app name")?
-        .to_string_lossy()
-        .to_string();
-
-    let _ = window.emit(
-        "install_progress",
-        InstallProgress {
-            app_id: app_id.to_string(),
-            progress: 60.0,
-            status: format!("Copying {} to Applications...", app_name),
-        },
-    );
-
     // Copy app to Applications folder
-    let applications_dir = get_applications_directory()?;
-    let destination = applications_dir.join(&app_name);
-
-    // Remove if already exists
-    if destination.exists() {
-        fs::remove_dir_all(&destination)
-            .map_err(|e| format!("Failed to remove existing app: {}", e))?;
-    }
-
-    copy_dir_all(&app_bundle, &destination).map_err(|e| format!("Failed to copy app: {}", e))?;
+    let app_name = copy_app_bundle(app_id, &app_bundle, app)?;
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.to_string(),
@@ -536,7 +716,7 @@ async fn install_dmg(
         eprintln!("Successfully unmounted DMG using force-eject");
     }
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_complete",
         InstallComplete {
             app_id: app_id.to_string(),
@@ -554,11 +734,11 @@ async fn install_dmg(
 async fn install_pkg(
     app_id: &str,
     file_path: &str,
-    window: &tauri::Window,
+    app: &tauri::AppHandle,
 ) -> Result<String, String> {
     use std::process::Command;
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.to_string(),
@@ -578,7 +758,7 @@ async fn install_pkg(
             "Failed to install PKG: {}",
             String::from_utf8_lossy(&output.stderr)
         );
-        let _ = window.emit(
+        let _ = app.emit(
             "install_complete",
             InstallComplete {
                 app_id: app_id.to_string(),
@@ -589,7 +769,7 @@ async fn install_pkg(
         return Err(error_msg);
     }
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_progress",
         InstallProgress {
             app_id: app_id.to_string(),
@@ -598,7 +778,7 @@ async fn install_pkg(
         },
     );
 
-    let _ = window.emit(
+    let _ = app.emit(
         "install_complete",
         InstallComplete {
             app_id: app_id.to_string(),
@@ -619,7 +799,7 @@ fn get_applications_directory() -> Result<PathBuf, String> {
 }
 
 // Helper function to recursively copy directories
-fn copy_dir_all(src: &PathBuf, dst: &PathBuf) -> std::io::Result<()> {
+fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
     eprintln!("Copying from {:?} to {:?}", src, dst);
     fs::create_dir_all(&dst).map_err(|e| {
         eprintln!("Failed to create directory {:?}: {}", dst, e);
@@ -0,0 +1,174 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::catalog;
+use crate::commands::App;
+use crate::fetcher::RegistryFetcher;
+use crate::registry::{self, RegistrySource};
+use crate::settings;
+
+const CURATED_PATH: &str = "curated.json";
+
+#[derive(Debug, Deserialize)]
+struct CuratedConfig {
+    #[serde(default)]
+    sections: Vec<CuratedSectionConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CuratedSectionConfig {
+    id: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    apps: Vec<String>,
+    // Newer name for `apps`, preferred when both are present
+    app_ids: Option<Vec<String>>,
+    // RFC 3339 timestamps or plain YYYY-MM-DD dates; the end date is inclusive
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CuratedSection {
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub apps: Vec<App>,
+}
+
+// Date-only values start at midnight UTC; an inclusive end date covers the whole day
+fn parse_schedule_date(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let date = if end_of_day { date.succ_opt()? } else { date };
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+impl CuratedSectionConfig {
+    fn is_scheduled_now(&self, now: DateTime<Utc>) -> bool {
+        let bound = |value: &Option<String>, end_of_day: bool| {
+            let value = value.as_deref()?;
+            let parsed = parse_schedule_date(value, end_of_day);
+            if parsed.is_none() {
+                eprintln!(
+                    "Warning: Ignoring invalid date {:?} in curated section {:?}",
+                    value, self.id
+                );
+            }
+            parsed
+        };
+
+        if let Some(start) = bound(&self.start_date, false) {
+            if now < start {
+                return false;
+            }
+        }
+        if let Some(end) = bound(&self.end_date, true) {
+            if now >= end {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+async fn fetch_config(
+    fetcher: &RegistryFetcher,
+    source: &RegistrySource,
+) -> Result<Option<CuratedConfig>, String> {
+    match registry::fetch_source_file(fetcher, source, CURATED_PATH).await {
+        Ok(bytes) => registry::parse_json(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse curated sections: {}", e)),
+        // Curated sections are optional for a registry
+        Err(e) if e.not_found => Ok(None),
+        Err(e) => Err(format!("Failed to fetch curated sections: {}", e)),
+    }
+}
+
+// Resolve the curated sections of every source against the loaded catalog
+pub async fn load_curated(client: &reqwest::Client) -> Result<Vec<CuratedSection>, String> {
+    let catalog = catalog::current_catalog(client).await?;
+    let apps_by_id: HashMap<&str, &App> = catalog
+        .apps
+        .iter()
+        .map(|app| (app.id.as_str(), app))
+        .collect();
+
+    let settings = settings::current_settings();
+    let sources = registry::ordered_sources(&settings.registries);
+    let fetcher = RegistryFetcher::new(client.clone(), settings.registry_fetch);
+
+    let results =
+        futures_util::future::join_all(sources.iter().map(|source| fetch_config(&fetcher, source)))
+            .await;
+
+    let now = Utc::now();
+    let mut seen_sections = HashSet::new();
+    let mut sections = vec![];
+    let mut errors = vec![];
+
+    for (source, result) in sources.iter().zip(results) {
+        let config = match result {
+            Ok(Some(config)) => config,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Registry {}: {}", source.id, e);
+                errors.push(format!("{}: {}", source.id, e));
+                continue;
+            }
+        };
+
+        for section in config.sections {
+            if !section.is_scheduled_now(now) {
+                continue;
+            }
+
+            let id = section
+                .id
+                .clone()
+                .or_else(|| section.title.clone())
+                .unwrap_or_else(|| "untitled-section".to_string());
+            // Higher priority sources win when section ids collide
+            if !seen_sections.insert(id.clone()) {
+                continue;
+            }
+
+            let app_ids = section.app_ids.as_ref().unwrap_or(&section.apps);
+            let apps = app_ids
+                .iter()
+                .filter_map(|app_id| {
+                    let app = apps_by_id.get(app_id.as_str()).map(|app| (*app).clone());
+                    if app.is_none() {
+                        eprintln!("Curated section {} references unknown app {}", id, app_id);
+                    }
+                    app
+                })
+                .collect();
+
+            sections.push(CuratedSection {
+                title: section
+                    .title
+                    .clone()
+                    .or_else(|| section.id.clone())
+                    .unwrap_or_else(|| "Untitled".to_string()),
+                id,
+                description: section.description,
+                apps,
+            });
+        }
+    }
+
+    if sections.is_empty() && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    Ok(sections)
+}
@@ -0,0 +1,949 @@
+use futures_util::stream::StreamExt;
+use once_cell::sync::Lazy;
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::artifacts::Artifact;
+use crate::commands::{DownloadComplete, DownloadErrorKind, DownloadPhase};
+use crate::formats;
+use crate::http::HttpClient;
+use crate::progress::ProgressReporter;
+use crate::settings;
+use crate::throttle::{self, TokenBucket};
+
+const DOWNLOADS_FILE_NAME: &str = "downloads.json";
+
+// Never wait longer than this between download retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// Stop controls of every active job, keyed by app id
+static ACTIVE_DOWNLOADS: Lazy<Mutex<HashMap<String, JobControl>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DownloadSettings {
+    // Downloads running at once; the rest wait in the queue
+    pub max_concurrent_downloads: usize,
+    // Bandwidth caps in bytes per second, 0 for unlimited
+    pub max_bytes_per_second: u64,
+    pub per_download_bytes_per_second: u64,
+    // Retries after the first attempt for timeouts, dropped connections and 5xx
+    pub max_retries: u32,
+    // Delay before the first retry, doubled on every following attempt
+    pub retry_base_delay_ms: u64,
+    // Applies to every request made through the shared HTTP client; 0 disables
+    pub connect_timeout_secs: u64,
+    // Longest wait for the response or the next chunk of the body; 0 disables
+    pub read_timeout_secs: u64,
+    // Slower than this over a whole stall window counts as stalled; 0 disables
+    pub min_bytes_per_second: u64,
+    pub stall_window_secs: u64,
+}
+
+// Longer timeouts are almost certainly typos, and huge ones overflow deadlines
+const MAX_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+impl DownloadSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent_downloads == 0 {
+            return Err("At least one download must be allowed to run".to_string());
+        }
+        for (name, value) in [
+            ("Connect timeout", self.connect_timeout_secs),
+            ("Read timeout", self.read_timeout_secs),
+            ("Stall window", self.stall_window_secs),
+        ] {
+            if value > MAX_TIMEOUT_SECS {
+                return Err(format!(
+                    "{} must be at most {} seconds",
+                    name, MAX_TIMEOUT_SECS
+                ));
+            }
+        }
+        if self.min_bytes_per_second > 0 && self.stall_window_secs == 0 {
+            return Err("Stall window must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            max_concurrent_downloads: 3,
+            max_bytes_per_second: 0,
+            per_download_bytes_per_second: 0,
+            max_retries: 3,
+            retry_base_delay_ms: 1000,
+            connect_timeout_secs: 15,
+            read_timeout_secs: 30,
+            min_bytes_per_second: 1024,
+            stall_window_secs: 30,
+        }
+    }
+}
+
+pub enum DownloadOutcome {
+    // `url` is the mirror the file came from
+    Completed { file_path: PathBuf, url: String },
+    Cancelled,
+    Paused,
+}
+
+// Finished downloads keyed by app id, used to reuse artifacts while offline
+static COMPLETED_DOWNLOADS: Lazy<Mutex<HashMap<String, CompletedDownload>>> =
+    Lazy::new(|| Mutex::new(load_completed()));
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedDownload {
+    pub app_id: String,
+    // Mirror the file was downloaded from
+    pub url: String,
+    pub file_path: String,
+    pub size: u64,
+}
+
+fn load_completed() -> HashMap<String, CompletedDownload> {
+    settings::get_app_data_directory()
+        .ok()
+        .and_then(|dir| fs::read(dir.join(DOWNLOADS_FILE_NAME)).ok())
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_completed(completed: &HashMap<String, CompletedDownload>) -> Result<(), String> {
+    let path = settings::get_app_data_directory()?.join(DOWNLOADS_FILE_NAME);
+    let contents = serde_json::to_vec_pretty(completed)
+        .map_err(|e| format!("Failed to serialize downloads: {}", e))?;
+    settings::write_atomic(&path, &contents)
+        .map_err(|e| format!("Failed to write downloads: {}", e))
+}
+
+pub fn record_completed(app_id: &str, url: &str, file_path: &Path) -> Result<(), String> {
+    let size = fs::metadata(file_path)
+        .map_err(|e| format!("Failed to read downloaded file: {}", e))?
+        .len();
+
+    let mut completed = COMPLETED_DOWNLOADS.lock().unwrap();
+    completed.insert(
+        app_id.to_string(),
+        CompletedDownload {
+            app_id: app_id.to_string(),
+            url: url.to_string(),
+            file_path: file_path.to_string_lossy().to_string(),
+            size,
+        },
+    );
+    write_completed(&completed)
+}
+
+// A previous download from any of the given URLs that is still intact on disk
+pub fn find_completed(app_id: &str, urls: &[&str]) -> Option<CompletedDownload> {
+    let completed = COMPLETED_DOWNLOADS.lock().unwrap();
+    let entry = completed.get(app_id)?;
+    if !urls.contains(&entry.url.as_str()) {
+        return None;
+    }
+    let size = fs::metadata(&entry.file_path).ok()?.len();
+    if size != entry.size {
+        return None;
+    }
+    Some(entry.clone())
+}
+
+// Stored next to a .part file so an interrupted download can be resumed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    total: u64,
+    // Name picked from the first response, kept when resuming
+    #[serde(default)]
+    file_name: Option<String>,
+    // Expected hash of the whole file; when known, another mirror may continue it
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+fn get_downloads_directory() -> Result<PathBuf, String> {
+    let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
+    let downloads = PathBuf::from(home).join("Downloads");
+
+    // Create downloads directory if it doesn't exist
+    fs::create_dir_all(&downloads)
+        .map_err(|e| format!("Failed to create downloads directory: {}", e))?;
+
+    Ok(downloads)
+}
+
+// The .part file and its sidecar; the final name is only known once the
+// server responds
+fn part_paths(downloads_dir: &Path, app_id: &str) -> (PathBuf, PathBuf) {
+    (
+        downloads_dir.join(format!("{}.download.part", app_id)),
+        downloads_dir.join(format!("{}.download.part.json", app_id)),
+    )
+}
+
+// Start and complete length of a `bytes start-end/total` Content-Range;
+// the total is None when the server sends `*`
+fn parse_content_range(header: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = header.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+// Full size of a download resumed by a 206 response, or None when the
+// response does not continue exactly where the part file ends
+fn resumed_total(content_range: Option<&str>, existing_len: u64, remaining: u64) -> Option<u64> {
+    let (start, total) = parse_content_range(content_range?)?;
+    if start != existing_len {
+        return None;
+    }
+    Some(total.unwrap_or(existing_len + remaining))
+}
+
+fn load_partial(meta_path: &Path) -> Option<PartialDownload> {
+    serde_json::from_slice(&fs::read(meta_path).ok()?).ok()
+}
+
+fn save_partial(meta_path: &Path, partial: &PartialDownload) -> Result<(), String> {
+    let contents = serde_json::to_vec(partial)
+        .map_err(|e| format!("Failed to serialize download state: {}", e))?;
+    fs::write(meta_path, contents).map_err(|e| format!("Failed to write download state: {}", e))
+}
+
+fn checksum_mismatch(file_name: &str, detail: String) -> String {
+    format!("Checksum mismatch for {}: {}", file_name, detail)
+}
+
+// Compare what was downloaded against the size and hash listed in the manifest
+fn verify_download(
+    artifact: &Artifact,
+    file_name: &str,
+    size: u64,
+    sha256: &str,
+) -> Result<(), String> {
+    if let Some(expected) = artifact.size {
+        if expected != size {
+            return Err(checksum_mismatch(
+                file_name,
+                format!("expected {} bytes but received {}", expected, size),
+            ));
+        }
+    }
+    if let Some(expected) = &artifact.sha256 {
+        if !expected.eq_ignore_ascii_case(sha256) {
+            return Err(checksum_mismatch(
+                file_name,
+                format!("expected sha256 {} but got {}", expected, sha256),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(path: &Path, hasher: &mut Sha256) -> Result<(), String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to read downloaded file: {}", e))?;
+    std::io::copy(&mut file, hasher)
+        .map_err(|e| format!("Failed to read downloaded file: {}", e))?;
+    Ok(())
+}
+
+// Pausing stops the job the same way as cancelling, with the flag set first
+#[derive(Clone)]
+struct JobControl {
+    token: CancellationToken,
+    pause: Arc<AtomicBool>,
+}
+
+// Registration of an active job, from the moment the queue starts it until
+// its download ends. Dropping it on any exit path unregisters the token so it
+// can never affect a later download.
+pub struct DownloadJob {
+    app_id: String,
+    control: JobControl,
+}
+
+impl DownloadJob {
+    pub fn start(app_id: &str) -> Result<Self, String> {
+        let mut active = ACTIVE_DOWNLOADS.lock().unwrap();
+        if active.contains_key(app_id) {
+            return Err(format!("{} is already downloading", app_id));
+        }
+        let control = JobControl {
+            token: CancellationToken::new(),
+            pause: Arc::new(AtomicBool::new(false)),
+        };
+        active.insert(app_id.to_string(), control.clone());
+        Ok(DownloadJob {
+            app_id: app_id.to_string(),
+            control,
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.pause.load(Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.token.is_cancelled() && !self.is_paused()
+    }
+
+    // Unregister once the download is over, so later cancel requests are
+    // refused instead of lost; returns whether one arrived before that
+    pub fn finish(self) -> bool {
+        ACTIVE_DOWNLOADS.lock().unwrap().remove(&self.app_id);
+        self.is_cancelled()
+    }
+}
+
+impl Drop for DownloadJob {
+    fn drop(&mut self) {
+        ACTIVE_DOWNLOADS.lock().unwrap().remove(&self.app_id);
+    }
+}
+
+// Cancel a running download; returns false when there is none. Cancelling
+// wins over an earlier pause request that has not taken effect yet.
+pub fn request_cancel(app_id: &str) -> bool {
+    match ACTIVE_DOWNLOADS.lock().unwrap().get(app_id) {
+        Some(control) => {
+            control.pause.store(false, Ordering::SeqCst);
+            control.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+// Stop a running download but keep its partial file; returns false when there is none
+pub fn request_pause(app_id: &str) -> bool {
+    match ACTIVE_DOWNLOADS.lock().unwrap().get(app_id) {
+        Some(control) => {
+            control.pause.store(true, Ordering::SeqCst);
+            control.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+fn emit_success(app: &tauri::AppHandle, app_id: &str, file_path: &str, url: &str) {
+    let _ = app.emit(
+        "download_complete",
+        DownloadComplete {
+            app_id: app_id.to_string(),
+            file_path: file_path.to_string(),
+            success: true,
+            error: None,
+            error_kind: None,
+            source_url: Some(url.to_string()),
+        },
+    );
+}
+
+fn emit_failure(app: &tauri::AppHandle, app_id: &str, kind: DownloadErrorKind, error: &str) {
+    let _ = app.emit(
+        "download_complete",
+        DownloadComplete {
+            app_id: app_id.to_string(),
+            file_path: String::new(),
+            success: false,
+            error: Some(error.to_string()),
+            error_kind: Some(kind),
+            source_url: None,
+        },
+    );
+}
+
+pub fn emit_cancelled(app: &tauri::AppHandle, app_id: &str) {
+    emit_failure(
+        app,
+        app_id,
+        DownloadErrorKind::Cancelled,
+        "Download cancelled",
+    );
+}
+
+// Why a download attempt failed, and whether trying again may help
+struct DownloadFailure {
+    message: String,
+    retryable: bool,
+    // Stalled or ChecksumMismatch when the failure is more specific than
+    // retryable or fatal
+    kind: Option<DownloadErrorKind>,
+}
+
+impl DownloadFailure {
+    fn fatal(message: String) -> Self {
+        DownloadFailure {
+            message,
+            retryable: false,
+            kind: None,
+        }
+    }
+
+    fn checksum_mismatch(message: String) -> Self {
+        DownloadFailure {
+            message,
+            retryable: false,
+            kind: Some(DownloadErrorKind::ChecksumMismatch),
+        }
+    }
+
+    // The server stopped sending or became too slow; retried like other transient failures
+    fn stalled(message: String) -> Self {
+        DownloadFailure {
+            message,
+            retryable: true,
+            kind: Some(DownloadErrorKind::Stalled),
+        }
+    }
+
+    // Timeouts, refused or reset connections and truncated bodies are transient
+    fn from_reqwest(context: &str, e: &reqwest::Error) -> Self {
+        DownloadFailure {
+            message: format!("{}: {}", context, e),
+            retryable: e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            kind: None,
+        }
+    }
+
+    // Server errors and rate limiting may clear up; other client errors will not
+    fn from_status(status: StatusCode) -> Self {
+        DownloadFailure {
+            message: format!("Failed to download: HTTP {}", status),
+            retryable: status.is_server_error()
+                || status == StatusCode::REQUEST_TIMEOUT
+                || status == StatusCode::TOO_MANY_REQUESTS,
+            kind: None,
+        }
+    }
+}
+
+// Fails a transfer whose average speed over a full window is below the minimum
+struct StallDetector {
+    min_bytes_per_second: u64,
+    window: Duration,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl StallDetector {
+    fn new(config: &DownloadSettings) -> Self {
+        StallDetector {
+            min_bytes_per_second: config.min_bytes_per_second,
+            window: Duration::from_secs(config.stall_window_secs.max(1)),
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+
+    fn record(&mut self, bytes: u64) -> Result<(), DownloadFailure> {
+        self.window_bytes += bytes;
+        let elapsed = self.window_start.elapsed();
+        if self.min_bytes_per_second == 0 || elapsed < self.window {
+            return Ok(());
+        }
+
+        let speed = self.window_bytes as f64 / elapsed.as_secs_f64();
+        if speed < self.min_bytes_per_second as f64 {
+            return Err(DownloadFailure::stalled(format!(
+                "Download stalled: {:.0} bytes/s over the last {}s is below the minimum of {} bytes/s",
+                speed,
+                elapsed.as_secs(),
+                self.min_bytes_per_second
+            )));
+        }
+        self.window_start = Instant::now();
+        self.window_bytes = 0;
+        Ok(())
+    }
+
+    // Time spent waiting on our own bandwidth limit is not the server's fault
+    fn exclude(&mut self, paused: Duration) {
+        self.window_start += paused;
+    }
+}
+
+// None when `limit` passed before the future finished
+async fn idle_timeout<F: std::future::Future>(
+    limit: Option<Duration>,
+    future: F,
+) -> Option<F::Output> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+// Result of a single attempt that did not fail; None when cancelled or paused
+type AttemptResult = Result<Option<PathBuf>, DownloadFailure>;
+
+fn retry_delay(config: &DownloadSettings, attempt: u32) -> Duration {
+    let delay = Duration::from_millis(config.retry_base_delay_ms)
+        .saturating_mul(2u32.saturating_pow(attempt));
+    delay.min(MAX_RETRY_DELAY)
+}
+
+// Download a single artifact, emitting progress events. Data is written to a
+// .part file that survives cancellation, errors and restarts, and is resumed
+// with a Range request when the server still has the same file. The bytes are
+// hashed as they arrive and checked against the artifact's sha256 and size.
+// A paused download keeps its .part file and continues from there. Every
+// mirror is tried in turn before transient failures are retried with
+// exponential backoff, resuming from the last byte written.
+pub async fn download_artifact(
+    job: &DownloadJob,
+    artifact: &Artifact,
+    app: &tauri::AppHandle,
+) -> Result<DownloadOutcome, String> {
+    let app_id = job.app_id.as_str();
+    // Every error is reported to the UI, which waits for download_complete
+    let fail = |message: String| {
+        emit_failure(app, app_id, DownloadErrorKind::Fatal, &message);
+        Err(message)
+    };
+    let urls = artifact.urls();
+    if urls.is_empty() {
+        return fail(format!("No download URL for app: {}", app_id));
+    }
+    let config = settings::current_settings().downloads;
+    let mut reporter = ProgressReporter::new(app, app_id);
+    let stopped = |reporter: &mut ProgressReporter| {
+        if job.is_paused() {
+            reporter.emit(DownloadPhase::Paused);
+            return Ok(DownloadOutcome::Paused);
+        }
+        emit_cancelled(app, app_id);
+        Ok(DownloadOutcome::Cancelled)
+    };
+    let downloads_dir = match get_downloads_directory() {
+        Ok(dir) => dir,
+        Err(e) => return fail(format!("Failed to get downloads directory: {}", e)),
+    };
+    let (part_path, meta_path) = part_paths(&downloads_dir, app_id);
+    let client = app.state::<HttpClient>().get();
+
+    let mut attempt = 0;
+    let mut mirror = 0;
+    // Whether any mirror failed in a way worth retrying during this round
+    let mut round_retryable = false;
+    let failure = loop {
+        let download_url = urls[mirror];
+        let result = download_attempt(
+            job,
+            &config,
+            &client,
+            artifact,
+            download_url,
+            &downloads_dir,
+            &mut reporter,
+        )
+        .await;
+        let failure = match result {
+            Ok(Some(file_path)) => {
+                if let Err(e) = record_completed(app_id, download_url, &file_path) {
+                    eprintln!("Warning: {}", e);
+                }
+                emit_success(app, app_id, &file_path.to_string_lossy(), download_url);
+                return Ok(DownloadOutcome::Completed {
+                    file_path,
+                    url: download_url.to_string(),
+                });
+            }
+            Ok(None) => return stopped(&mut reporter),
+            Err(failure) => failure,
+        };
+        round_retryable |= failure.retryable;
+
+        if mirror + 1 < urls.len() {
+            eprintln!(
+                "Download of {} from {} failed ({}), trying the next mirror",
+                app_id, download_url, failure.message
+            );
+            mirror += 1;
+            continue;
+        }
+
+        if !round_retryable || attempt >= config.max_retries {
+            break DownloadFailure {
+                message: if urls.len() > 1 {
+                    format!(
+                        "All {} mirrors failed, last error: {}",
+                        urls.len(),
+                        failure.message
+                    )
+                } else {
+                    failure.message
+                },
+                retryable: round_retryable,
+                kind: failure.kind,
+            };
+        }
+
+        let delay = retry_delay(&config, attempt);
+        eprintln!(
+            "Download of {} failed ({}), retrying in {:?}",
+            app_id, failure.message, delay
+        );
+        tokio::select! {
+            _ = job.control.token.cancelled() => return stopped(&mut reporter),
+            _ = tokio::time::sleep(delay) => {}
+        }
+        attempt += 1;
+        mirror = 0;
+        round_retryable = false;
+    };
+
+    if failure.retryable {
+        // Offline: reuse the artifact from an earlier complete download
+        let existing = find_completed(app_id, &urls).filter(|existing| {
+            let mut hasher = Sha256::new();
+            hash_file(Path::new(&existing.file_path), &mut hasher).is_ok()
+                && verify_download(
+                    artifact,
+                    &existing.file_path,
+                    existing.size,
+                    &hex::encode(hasher.finalize()),
+                )
+                .is_ok()
+        });
+        if let Some(existing) = existing {
+            eprintln!(
+                "Download failed ({}), reusing existing file: {}",
+                failure.message, existing.file_path
+            );
+            emit_success(app, app_id, &existing.file_path, &existing.url);
+            return Ok(DownloadOutcome::Completed {
+                file_path: PathBuf::from(existing.file_path),
+                url: existing.url,
+            });
+        }
+    } else {
+        // Nothing to resume from after a fatal failure
+        let _ = fs::remove_file(&part_path);
+        let _ = fs::remove_file(&meta_path);
+    }
+
+    let kind = failure.kind.unwrap_or(if failure.retryable {
+        DownloadErrorKind::Retryable
+    } else {
+        DownloadErrorKind::Fatal
+    });
+    emit_failure(app, app_id, kind, &failure.message);
+    Err(failure.message)
+}
+
+// One request to one mirror, continuing from whatever the .part file holds
+async fn download_attempt(
+    job: &DownloadJob,
+    config: &DownloadSettings,
+    client: &reqwest::Client,
+    artifact: &Artifact,
+    download_url: &str,
+    downloads_dir: &Path,
+    reporter: &mut ProgressReporter,
+) -> AttemptResult {
+    let (part_path, meta_path) = part_paths(downloads_dir, &job.app_id);
+    let (part_path, meta_path) = (part_path.as_path(), meta_path.as_path());
+
+    // Resume a partial file from the same URL, or from another mirror when the
+    // checksum will show whether the pieces belong to the same file
+    let partial = load_partial(meta_path).filter(|p| {
+        p.url == download_url || (artifact.sha256.is_some() && p.sha256 == artifact.sha256)
+    });
+    // If-Range makes the server send the whole file if it changed since;
+    // weak ETags are not allowed there, so fall back to Last-Modified.
+    // Validators from another mirror mean nothing to this one.
+    let validator = partial
+        .as_ref()
+        .filter(|p| p.url == download_url)
+        .and_then(|p| {
+            p.etag
+                .as_ref()
+                .filter(|etag| !etag.starts_with("W/"))
+                .or(p.last_modified.as_ref())
+        })
+        .cloned();
+    // Without a validator or a checksum nothing would notice a changed file
+    // being appended to the old bytes, so start over
+    let can_resume = validator.is_some() || artifact.sha256.is_some();
+    let existing_len = match &partial {
+        Some(_) if can_resume => fs::metadata(part_path).map(|m| m.len()).unwrap_or(0),
+        _ => 0,
+    };
+
+    // Emit start event
+    reporter.begin(existing_len, partial.as_ref().map(|p| p.total).unwrap_or(0));
+    reporter.emit(DownloadPhase::Starting);
+
+    let read_timeout = Some(config.read_timeout_secs)
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs);
+    let timed_out = || {
+        DownloadFailure::stalled(format!(
+            "Download stalled: no data received for {}s",
+            config.read_timeout_secs
+        ))
+    };
+    let mut request = client.get(download_url);
+    if existing_len > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing_len));
+        if let Some(validator) = &validator {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+
+    // Dropping the request future aborts the connection right away
+    let response = tokio::select! {
+        _ = job.control.token.cancelled() => return Ok(None),
+        sent = idle_timeout(read_timeout, request.send()) => sent,
+    }
+    .ok_or_else(timed_out)?
+    .map_err(|e| DownloadFailure::from_reqwest("Failed to download", &e))?;
+
+    let status = response.status();
+    let part_is_complete = status == StatusCode::RANGE_NOT_SATISFIABLE
+        && partial.as_ref().map(|p| p.total) == Some(existing_len);
+
+    let (downloaded, total_size) = if part_is_complete {
+        (existing_len, existing_len)
+    } else if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok());
+        let remaining = response.content_length().unwrap_or(0);
+        match resumed_total(content_range, existing_len, remaining) {
+            Some(total) => (existing_len, total),
+            // Bytes from somewhere else would corrupt the file; start over
+            None => {
+                let _ = fs::remove_file(part_path);
+                let _ = fs::remove_file(meta_path);
+                return Err(DownloadFailure {
+                    message: format!("Server did not resume at byte {}", existing_len),
+                    retryable: true,
+                    kind: None,
+                });
+            }
+        }
+    } else if status.is_success() {
+        // Fresh download, or the server ignored the range / the file changed
+        (0, response.content_length().unwrap_or(0))
+    } else {
+        return Err(DownloadFailure::from_status(status));
+    };
+
+    // Name the file the way the server does; a resumed download keeps the
+    // name chosen when it started
+    let file_name = partial
+        .as_ref()
+        .filter(|_| downloaded > 0)
+        .and_then(|p| p.file_name.clone())
+        .or_else(|| {
+            response
+                .headers()
+                .get(CONTENT_DISPOSITION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(formats::file_name_from_content_disposition)
+        })
+        .or_else(|| formats::file_name_from_url(response.url()))
+        .unwrap_or_else(|| job.app_id.clone());
+
+    // Bytes already on disk count towards the hash
+    let mut hasher = Sha256::new();
+    if downloaded > 0 {
+        hash_file(part_path, &mut hasher).map_err(DownloadFailure::fatal)?;
+    }
+
+    reporter.begin(downloaded, total_size);
+
+    if !part_is_complete {
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        save_partial(
+            meta_path,
+            &PartialDownload {
+                url: download_url.to_string(),
+                etag: header_value(ETAG),
+                last_modified: header_value(LAST_MODIFIED),
+                total: total_size,
+                file_name: Some(file_name.clone()),
+                sha256: artifact.sha256.clone(),
+            },
+        )
+        .map_err(DownloadFailure::fatal)?;
+
+        // Append when resuming, otherwise start the .part file over
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(downloaded > 0)
+            .truncate(downloaded == 0)
+            .open(part_path)
+            .map_err(|e| DownloadFailure::fatal(format!("Failed to create file: {}", e)))?;
+
+        let mut stream = response.bytes_stream();
+        let mut file_writer = std::io::BufWriter::new(file);
+        let mut bucket = TokenBucket::new();
+        let mut stall = StallDetector::new(config);
+
+        loop {
+            // Waiting on the token as well means a stalled connection can still be cancelled
+            let chunk = tokio::select! {
+                _ = job.control.token.cancelled() => {
+                    // Keep the partial file so the download can be resumed later
+                    let _ = file_writer.flush();
+                    return Ok(None);
+                }
+                chunk = idle_timeout(read_timeout, stream.next()) => chunk,
+            };
+            let chunk = match chunk {
+                Some(chunk) => chunk,
+                None => {
+                    let _ = file_writer.flush();
+                    return Err(timed_out());
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    // Everything written so far is kept for the next attempt
+                    let _ = file_writer.flush();
+                    return Err(DownloadFailure::from_reqwest("Download error", &e));
+                }
+            };
+            file_writer
+                .write_all(&chunk)
+                .map_err(|e| DownloadFailure::fatal(format!("Failed to write to file: {}", e)))?;
+            hasher.update(&chunk);
+
+            // Emits are throttled by the reporter
+            reporter.advance(chunk.len() as u64);
+
+            if let Err(failure) = stall.record(chunk.len() as u64) {
+                let _ = file_writer.flush();
+                return Err(failure);
+            }
+
+            // Stay within the bandwidth limits, still reacting to cancellation
+            let delay = throttle::delay_for(&mut bucket, chunk.len() as u64);
+            if !delay.is_zero() {
+                tokio::select! {
+                    _ = job.control.token.cancelled() => {
+                        let _ = file_writer.flush();
+                        return Ok(None);
+                    }
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                stall.exclude(delay);
+            }
+        }
+
+        file_writer
+            .flush()
+            .map_err(|e| DownloadFailure::fatal(format!("Failed to flush file: {}", e)))?;
+    }
+
+    reporter.emit(DownloadPhase::Verifying);
+    let sha256 = hex::encode(hasher.finalize());
+    // A checksum mismatch is fatal for this mirror; drop the bad file so the
+    // next one starts over
+    if let Err(e) = verify_download(artifact, &file_name, reporter.downloaded(), &sha256) {
+        let _ = fs::remove_file(part_path);
+        let _ = fs::remove_file(meta_path);
+        return Err(DownloadFailure::checksum_mismatch(e));
+    }
+
+    // The content decides the extension, whatever the URL claimed
+    let file_name = match formats::detect(part_path).map_err(DownloadFailure::fatal)? {
+        Some(format) => formats::with_format_extension(&file_name, format),
+        None => file_name,
+    };
+    let file_path =
+        move_into_place(part_path, downloads_dir, &file_name).map_err(DownloadFailure::fatal)?;
+    let _ = fs::remove_file(meta_path);
+
+    Ok(Some(file_path))
+}
+
+// Move the finished .part file to `file_name`, numbering the name instead of
+// replacing a file that is already there. Linking fails when the target
+// exists, so a file appearing in the meantime is not overwritten either.
+fn move_into_place(
+    part_path: &Path,
+    downloads_dir: &Path,
+    file_name: &str,
+) -> Result<PathBuf, String> {
+    let mut file_path = downloads_dir.join(file_name);
+    let mut n = 0;
+    loop {
+        match fs::hard_link(part_path, &file_path) {
+            Ok(()) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                n += 1;
+                file_path = downloads_dir.join(formats::numbered_file_name(file_name, n));
+            }
+            Err(e) => return Err(format!("Failed to move downloaded file into place: {}", e)),
+        }
+    }
+    let _ = fs::remove_file(part_path);
+    Ok(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(parse_content_range("bytes 0-99/100"), Some((0, Some(100))));
+        assert_eq!(parse_content_range("bytes 10-99/*"), Some((10, None)));
+    }
+
+    #[test]
+    fn rejects_malformed_content_range() {
+        assert_eq!(parse_content_range("bytes */100"), None);
+        assert_eq!(parse_content_range("items 0-99/100"), None);
+        assert_eq!(parse_content_range("bytes 0-99"), None);
+        assert_eq!(parse_content_range(""), None);
+    }
+
+    #[test]
+    fn resumes_only_at_the_end_of_the_part_file() {
+        assert_eq!(resumed_total(Some("bytes 10-99/100"), 10, 90), Some(100));
+        // Unknown total falls back to what is on disk plus the response length
+        assert_eq!(resumed_total(Some("bytes 10-99/*"), 10, 90), Some(100));
+        assert_eq!(resumed_total(Some("bytes 0-99/100"), 10, 100), None);
+        assert_eq!(resumed_total(Some("bytes 20-99/100"), 10, 80), None);
+        assert_eq!(resumed_total(Some("garbage"), 10, 90), None);
+        assert_eq!(resumed_total(None, 10, 90), None);
+    }
+}
@@ -0,0 +1,164 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Semaphore;
+
+use crate::registry::{FetchError, FetchStage};
+
+// Never wait longer than this between retries, whatever the server asks for
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RegistryFetchSettings {
+    // Maximum number of registry requests in flight at once
+    pub max_concurrent_requests: usize,
+    pub request_timeout_secs: u64,
+    // Retries after the first attempt for 429, 5xx and connection failures
+    pub max_retries: u32,
+    // Delay before the first retry, doubled on every following attempt
+    pub retry_base_delay_ms: u64,
+}
+
+impl Default for RegistryFetchSettings {
+    fn default() -> Self {
+        RegistryFetchSettings {
+            max_concurrent_requests: 8,
+            request_timeout_secs: 15,
+            max_retries: 3,
+            retry_base_delay_ms: 500,
+        }
+    }
+}
+
+pub struct FetchedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+// HTTP client wrapper shared by all registry requests of one catalog load
+#[derive(Clone)]
+pub struct RegistryFetcher {
+    client: reqwest::Client,
+    limiter: Arc<Semaphore>,
+    config: RegistryFetchSettings,
+}
+
+impl RegistryFetcher {
+    pub fn new(client: reqwest::Client, config: RegistryFetchSettings) -> Self {
+        RegistryFetcher {
+            client,
+            limiter: Arc::new(Semaphore::new(config.max_concurrent_requests.max(1))),
+            config,
+        }
+    }
+
+    // Send a request built by `build`, retrying with exponential backoff.
+    // The builder is called again for every attempt.
+    pub async fn fetch<F>(&self, url: &str, build: F) -> Result<FetchedResponse, FetchError>
+    where
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        let timeout = Duration::from_secs(self.config.request_timeout_secs.max(1));
+        let mut attempt = 0;
+
+        loop {
+            let result = {
+                // The permit is only held for the request itself, not while backing off
+                let _permit = self
+                    .limiter
+                    .acquire()
+                    .await
+                    .map_err(|e| FetchError::new(FetchStage::Network, e.to_string()))?;
+                self.send_once(url, build(&self.client).timeout(timeout))
+                    .await
+            };
+
+            let retry_after = match &result {
+                Ok(response) if is_retryable_status(response.status) => {
+                    Some(parse_retry_after(&response.headers))
+                }
+                Err((_, retryable)) if *retryable => Some(None),
+                _ => None,
+            };
+
+            match retry_after {
+                Some(server_delay) if attempt < self.config.max_retries => {
+                    let delay = server_delay
+                        .unwrap_or_else(|| self.backoff_delay(attempt))
+                        .min(MAX_RETRY_DELAY);
+                    eprintln!(
+                        "Retrying {} in {:?} (attempt {} of {})",
+                        url,
+                        delay,
+                        attempt + 1,
+                        self.config.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return result.map_err(|(e, _)| e),
+            }
+        }
+    }
+
+    async fn send_once(
+        &self,
+        url: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<FetchedResponse, (FetchError, bool)> {
+        let response = request.send().await.map_err(|e| {
+            let retryable = e.is_timeout() || e.is_connect();
+            (
+                FetchError::new(
+                    FetchStage::Network,
+                    format!("Failed to fetch {}: {}", url, e),
+                ),
+                retryable,
+            )
+        })?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(|e| {
+            (
+                FetchError::new(
+                    FetchStage::Network,
+                    format!("Failed to read response from {}: {}", url, e),
+                ),
+                e.is_timeout(),
+            )
+        })?;
+
+        Ok(FetchedResponse {
+            status,
+            headers,
+            body: body.to_vec(),
+        })
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt);
+        Duration::from_millis(self.config.retry_base_delay_ms.saturating_mul(factor))
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
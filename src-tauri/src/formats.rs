@@ -0,0 +1,298 @@
+use percent_encoding::percent_decode_str;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// UDIF disk images end with a 512 byte trailer that starts with "koly"
+const UDIF_TRAILER_LEN: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactFormat {
+    Dmg,
+    // Flat installer packages are xar archives
+    Pkg,
+    Zip,
+    TarGz,
+    TarXz,
+}
+
+impl ArtifactFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArtifactFormat::Dmg => "dmg",
+            ArtifactFormat::Pkg => "pkg",
+            ArtifactFormat::Zip => "zip",
+            ArtifactFormat::TarGz => "tar.gz",
+            ArtifactFormat::TarXz => "tar.xz",
+        }
+    }
+
+    // Fallback for files whose content could not be recognised
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let name = file_name.to_lowercase();
+        [
+            ArtifactFormat::TarGz,
+            ArtifactFormat::TarXz,
+            ArtifactFormat::Dmg,
+            ArtifactFormat::Pkg,
+            ArtifactFormat::Zip,
+        ]
+        .into_iter()
+        .find(|format| name.ends_with(&format!(".{}", format.extension())))
+    }
+}
+
+// Identify an artifact by its magic bytes rather than its name
+pub fn detect(path: &Path) -> Result<Option<ArtifactFormat>, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+
+    let mut header = [0u8; 6];
+    let read = file
+        .read(&mut header)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let header = &header[..read];
+
+    if header.starts_with(b"xar!") {
+        return Ok(Some(ArtifactFormat::Pkg));
+    }
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        return Ok(Some(ArtifactFormat::Zip));
+    }
+    if header.starts_with(&[0x1f, 0x8b]) {
+        return Ok(Some(ArtifactFormat::TarGz));
+    }
+    if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Ok(Some(ArtifactFormat::TarXz));
+    }
+
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?
+        .len();
+    if len >= UDIF_TRAILER_LEN {
+        let mut trailer = [0u8; 4];
+        file.seek(SeekFrom::Start(len - UDIF_TRAILER_LEN))
+            .and_then(|_| file.read_exact(&mut trailer))
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        if &trailer == b"koly" {
+            return Ok(Some(ArtifactFormat::Dmg));
+        }
+    }
+
+    Ok(None)
+}
+
+// Keep only the final path component and drop anything that could escape
+// the downloads directory or hide the file
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches('.')
+        .replace(|c: char| c.is_control() || c == ':', "_");
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+// File name from a Content-Disposition header; `filename*` (RFC 5987) wins
+// over plain `filename`
+pub fn file_name_from_content_disposition(header: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in header.split(';').skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'percent-encoded-name
+                let encoded = value.splitn(3, '\'').nth(2).unwrap_or(value);
+                extended = Some(percent_decode_str(encoded).decode_utf8_lossy().to_string());
+            }
+            "filename" => plain = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    extended
+        .or(plain)
+        .and_then(|name| sanitize_file_name(&name))
+}
+
+// Last path segment of the final (redirected) URL
+pub fn file_name_from_url(url: &reqwest::Url) -> Option<String> {
+    let segment = url.path_segments()?.rev().find(|s| !s.is_empty())?;
+    sanitize_file_name(&percent_decode_str(segment).decode_utf8_lossy())
+}
+
+// `name (1).dmg`, `name (2).dmg`, ... for the n-th alternative to a taken name
+pub fn numbered_file_name(file_name: &str, n: u32) -> String {
+    let extension_len = match ArtifactFormat::from_file_name(file_name) {
+        Some(format) => format.extension().len() + 1,
+        None => file_name.rfind('.').map_or(0, |dot| file_name.len() - dot),
+    };
+    let (stem, extension) = file_name.split_at(file_name.len() - extension_len);
+    format!("{} ({}){}", stem, n, extension)
+}
+
+// Make sure the name ends in the extension of what was actually downloaded
+pub fn with_format_extension(file_name: &str, format: ArtifactFormat) -> String {
+    let stem = match ArtifactFormat::from_file_name(file_name) {
+        Some(named) if named == format => return file_name.to_string(),
+        // A misleading extension is replaced rather than kept
+        Some(wrong) => &file_name[..file_name.len() - wrong.extension().len() - 1],
+        None => file_name,
+    };
+    format!("{}.{}", stem, format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Write `contents` to a fresh file in the temp directory and detect it
+    fn detect_bytes(name: &str, contents: &[u8]) -> Option<ArtifactFormat> {
+        let path = std::env::temp_dir().join(format!(
+            "fossintosh-formats-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        let format = detect(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        format
+    }
+
+    fn dmg(len: usize) -> Vec<u8> {
+        let mut contents = vec![0u8; len];
+        contents[len - 512..len - 508].copy_from_slice(b"koly");
+        contents
+    }
+
+    #[test]
+    fn detects_magic_signatures() {
+        assert_eq!(
+            detect_bytes("pkg", b"xar!\0\x1c"),
+            Some(ArtifactFormat::Pkg)
+        );
+        assert_eq!(
+            detect_bytes("zip", b"PK\x03\x04rest"),
+            Some(ArtifactFormat::Zip)
+        );
+        assert_eq!(
+            detect_bytes("empty-zip", b"PK\x05\x06"),
+            Some(ArtifactFormat::Zip)
+        );
+        assert_eq!(
+            detect_bytes("gz", &[0x1f, 0x8b, 0x08]),
+            Some(ArtifactFormat::TarGz)
+        );
+        assert_eq!(
+            detect_bytes("xz", &[0xfd, b'7', b'z', b'X', b'Z', 0x00, 0x00]),
+            Some(ArtifactFormat::TarXz)
+        );
+        assert_eq!(detect_bytes("dmg", &dmg(4096)), Some(ArtifactFormat::Dmg));
+        assert_eq!(
+            detect_bytes("dmg-trailer-only", &dmg(512)),
+            Some(ArtifactFormat::Dmg)
+        );
+    }
+
+    #[test]
+    fn detects_nothing_in_unknown_or_short_files() {
+        assert_eq!(detect_bytes("html", b"<!DOCTYPE html><html></html>"), None);
+        assert_eq!(detect_bytes("short", &[0u8; 100]), None);
+        assert_eq!(detect_bytes("single-byte", b"P"), None);
+        assert_eq!(detect_bytes("zero-length", b""), None);
+    }
+
+    #[test]
+    fn sanitize_strips_directories() {
+        assert_eq!(
+            sanitize_file_name("../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            sanitize_file_name("..\\..\\evil.dmg").as_deref(),
+            Some("evil.dmg")
+        );
+        assert_eq!(sanitize_file_name("dir/").as_deref(), None);
+        assert_eq!(sanitize_file_name("..").as_deref(), None);
+    }
+
+    #[test]
+    fn sanitize_strips_leading_dots() {
+        assert_eq!(
+            sanitize_file_name(".hidden.dmg").as_deref(),
+            Some("hidden.dmg")
+        );
+        assert_eq!(sanitize_file_name("...app.zip").as_deref(), Some("app.zip"));
+        assert_eq!(sanitize_file_name(" . ").as_deref(), None);
+        assert_eq!(sanitize_file_name("a:b\n.dmg").as_deref(), Some("a_b_.dmg"));
+    }
+
+    #[test]
+    fn content_disposition_prefers_extended_file_name() {
+        assert_eq!(
+            file_name_from_content_disposition(
+                "attachment; filename=\"fallback.dmg\"; filename*=UTF-8''Caf%C3%A9%20App.dmg"
+            )
+            .as_deref(),
+            Some("Café App.dmg")
+        );
+        assert_eq!(
+            file_name_from_content_disposition("attachment; filename=\"plain.zip\"").as_deref(),
+            Some("plain.zip")
+        );
+        assert_eq!(
+            file_name_from_content_disposition("attachment; filename=\"../../x.pkg\"").as_deref(),
+            Some("x.pkg")
+        );
+        assert_eq!(file_name_from_content_disposition("inline"), None);
+    }
+
+    #[test]
+    fn url_file_name_uses_last_segment() {
+        let url = reqwest::Url::parse("https://example.com/releases/My%20App.dmg/").unwrap();
+        assert_eq!(file_name_from_url(&url).as_deref(), Some("My App.dmg"));
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        assert_eq!(file_name_from_url(&url), None);
+    }
+
+    #[test]
+    fn numbering_keeps_the_whole_extension() {
+        assert_eq!(numbered_file_name("app.tar.gz", 1), "app (1).tar.gz");
+        assert_eq!(numbered_file_name("App.dmg", 2), "App (2).dmg");
+        assert_eq!(numbered_file_name("notes.txt", 3), "notes (3).txt");
+        assert_eq!(numbered_file_name("README", 1), "README (1)");
+    }
+
+    #[test]
+    fn replaces_misleading_extensions() {
+        assert_eq!(
+            with_format_extension("app.zip", ArtifactFormat::Dmg),
+            "app.dmg"
+        );
+        assert_eq!(
+            with_format_extension("app.tar.gz", ArtifactFormat::Zip),
+            "app.zip"
+        );
+        assert_eq!(
+            with_format_extension("App.DMG", ArtifactFormat::Dmg),
+            "App.DMG"
+        );
+        assert_eq!(
+            with_format_extension("download", ArtifactFormat::TarXz),
+            "download.tar.xz"
+        );
+        assert_eq!(
+            with_format_extension("app.gz", ArtifactFormat::TarGz),
+            "app.gz.tar.gz"
+        );
+    }
+}
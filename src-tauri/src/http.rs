@@ -0,0 +1,139 @@
+use reqwest::{Certificate, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::settings::Settings;
+
+const DEFAULT_USER_AGENT: &str = concat!("Fossintosh/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    // http://, https://, socks5:// or socks5h:// proxy for every request;
+    // unset falls back to the HTTP_PROXY / HTTPS_PROXY environment
+    pub proxy: Option<String>,
+    // Hosts that bypass the proxy, e.g. "localhost" or ".corp.example.com"
+    pub no_proxy: Vec<String>,
+    // PEM file with extra root certificates, e.g. of a TLS-intercepting proxy
+    pub ca_bundle: Option<String>,
+    // Unset sends the app name and version
+    pub user_agent: Option<String>,
+}
+
+// The one HTTP client used by all network code, kept in Tauri managed state.
+// Replaced when the settings change; requests already running keep the old one.
+pub struct HttpClient {
+    client: RwLock<reqwest::Client>,
+    // Why part of the saved network settings could not be applied at startup
+    error: RwLock<Option<String>>,
+}
+
+impl HttpClient {
+    // Broken saved settings must not stop the app from starting. Only the
+    // broken proxy or CA bundle is left out; the rest still applies.
+    pub fn new(settings: &Settings) -> Self {
+        let (client, error) = match build_client(settings) {
+            Ok(client) => (client, None),
+            Err(_) => {
+                let (client, errors) = build_client_lenient(settings);
+                let error = errors.join("; ");
+                eprintln!("Warning: {}", error);
+                (client, Some(error))
+            }
+        };
+        HttpClient {
+            client: RwLock::new(client),
+            error: RwLock::new(error),
+        }
+    }
+
+    // Clients share their connection pool, so this is cheap
+    pub fn get(&self) -> reqwest::Client {
+        self.client.read().unwrap().clone()
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, client: reqwest::Client) {
+        *self.client.write().unwrap() = client;
+        *self.error.write().unwrap() = None;
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn proxy(network: &NetworkSettings) -> Result<Option<Proxy>, String> {
+    let Some(url) = non_empty(&network.proxy) else {
+        return Ok(None);
+    };
+    let proxy = Proxy::all(url)
+        .map_err(|e| format!("Invalid proxy {}: {}", url, e))?
+        .no_proxy(NoProxy::from_string(&network.no_proxy.join(",")));
+    Ok(Some(proxy))
+}
+
+fn root_certificates(network: &NetworkSettings) -> Result<Vec<Certificate>, String> {
+    let Some(path) = non_empty(&network.ca_bundle) else {
+        return Ok(vec![]);
+    };
+    let pem = fs::read(path).map_err(|e| format!("Failed to read CA bundle {}: {}", path, e))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .map_err(|e| format!("Failed to parse CA bundle {}: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("No certificates found in CA bundle {}", path));
+    }
+    Ok(certificates)
+}
+
+fn build(
+    settings: &Settings,
+    proxy: Option<Proxy>,
+    certificates: Vec<Certificate>,
+) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(non_empty(&settings.network.user_agent).unwrap_or(DEFAULT_USER_AGENT));
+    if settings.downloads.connect_timeout_secs > 0 {
+        builder =
+            builder.connect_timeout(Duration::from_secs(settings.downloads.connect_timeout_secs));
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in certificates {
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+pub fn build_client(settings: &Settings) -> Result<reqwest::Client, String> {
+    let proxy = proxy(&settings.network)?;
+    let certificates = root_certificates(&settings.network)?;
+    build(settings, proxy, certificates)
+}
+
+// Client with every part of the settings that works, and the errors of the
+// parts that were left out
+fn build_client_lenient(settings: &Settings) -> (reqwest::Client, Vec<String>) {
+    let mut errors = vec![];
+    let proxy = proxy(&settings.network).unwrap_or_else(|e| {
+        errors.push(format!("{}, connecting without a proxy", e));
+        None
+    });
+    let certificates = root_certificates(&settings.network).unwrap_or_else(|e| {
+        errors.push(format!("{}, using the system certificates only", e));
+        vec![]
+    });
+    let client = build(settings, proxy, certificates).unwrap_or_else(|e| {
+        errors.push(format!("{}, using the default HTTP client", e));
+        reqwest::Client::new()
+    });
+    (client, errors)
+}
@@ -1,16 +1,50 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use tauri::Manager;
+
+mod artifacts;
+mod catalog;
+mod categories;
 mod commands;
+mod curated;
+mod downloads;
+mod fetcher;
+mod formats;
+mod http;
+mod progress;
+mod queue;
+mod registry;
+mod registry_cache;
+mod settings;
+mod signing;
+mod throttle;
+mod trust;
 
 fn main() {
     tauri::Builder::default()
+        .setup(|app| {
+            app.manage(http::HttpClient::new(&settings::current_settings()));
+            queue::init(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::fetch_apps,
+            commands::fetch_curated,
+            commands::list_categories,
             commands::download_app,
+            commands::list_jobs,
+            commands::set_download_priority,
+            commands::move_download,
             commands::cancel_download,
+            commands::pause_download,
+            commands::resume_download,
             commands::install_app,
+            commands::install_version,
             commands::check_updates,
             commands::search_apps,
+            commands::get_settings,
+            commands::update_settings,
+            commands::clear_registry_cache,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
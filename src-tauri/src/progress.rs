@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::commands::{DownloadPhase, DownloadProgress};
+
+// Never emit more often than this, however fast chunks arrive
+const MIN_EMIT_INTERVAL: Duration = Duration::from_millis(100);
+// Emit at least this often while data is flowing
+const MAX_EMIT_INTERVAL: Duration = Duration::from_millis(500);
+// Progress in percent that triggers an emit before MAX_EMIT_INTERVAL
+const EMIT_PERCENT_DELTA: f64 = 1.0;
+// Weight of the newest sample in the exponential moving average of the speed
+const SPEED_SMOOTHING: f64 = 0.3;
+
+// Turns a stream of chunk sizes into a bounded number of progress events
+pub struct ProgressReporter {
+    app: tauri::AppHandle,
+    app_id: String,
+    total: u64,
+    downloaded: u64,
+    last_emit: Instant,
+    last_emit_percent: f64,
+    sample_start: Instant,
+    sample_bytes: u64,
+    bytes_per_second: Option<f64>,
+}
+
+impl ProgressReporter {
+    pub fn new(app: &tauri::AppHandle, app_id: &str) -> Self {
+        let now = Instant::now();
+        ProgressReporter {
+            app: app.clone(),
+            app_id: app_id.to_string(),
+            total: 0,
+            downloaded: 0,
+            last_emit: now,
+            last_emit_percent: 0.0,
+            sample_start: now,
+            sample_bytes: 0,
+            bytes_per_second: None,
+        }
+    }
+
+    // Restart the speed measurement, e.g. once a response arrives. `downloaded`
+    // counts bytes already on disk from an earlier attempt.
+    pub fn begin(&mut self, downloaded: u64, total: u64) {
+        self.downloaded = downloaded;
+        self.total = total;
+        self.sample_start = Instant::now();
+        self.sample_bytes = downloaded;
+        self.bytes_per_second = None;
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    fn percent(&self) -> f64 {
+        if self.total > 0 {
+            (self.downloaded as f64 / self.total as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    // Record received bytes and emit if enough time or progress has passed
+    pub fn advance(&mut self, bytes: u64) {
+        self.downloaded += bytes;
+
+        let since_emit = self.last_emit.elapsed();
+        if since_emit < MIN_EMIT_INTERVAL {
+            return;
+        }
+        if since_emit < MAX_EMIT_INTERVAL
+            && self.percent() - self.last_emit_percent < EMIT_PERCENT_DELTA
+        {
+            return;
+        }
+
+        self.sample_speed();
+        self.emit(DownloadPhase::Downloading);
+    }
+
+    fn sample_speed(&mut self) {
+        let elapsed = self.sample_start.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+        let current = self.downloaded.saturating_sub(self.sample_bytes) as f64 / elapsed;
+        self.bytes_per_second = Some(match self.bytes_per_second {
+            Some(previous) => SPEED_SMOOTHING * current + (1.0 - SPEED_SMOOTHING) * previous,
+            None => current,
+        });
+        self.sample_start = Instant::now();
+        self.sample_bytes = self.downloaded;
+    }
+
+    // Emit right away, for phase changes the UI should not miss
+    pub fn emit(&mut self, phase: DownloadPhase) {
+        let bytes_per_second = match phase {
+            DownloadPhase::Downloading => self.bytes_per_second.unwrap_or(0.0),
+            _ => 0.0,
+        };
+        let eta_seconds = match phase {
+            DownloadPhase::Downloading if bytes_per_second > 0.0 && self.total > 0 => Some(
+                (self.total.saturating_sub(self.downloaded) as f64 / bytes_per_second).ceil()
+                    as u64,
+            ),
+            _ => None,
+        };
+
+        self.last_emit = Instant::now();
+        self.last_emit_percent = self.percent();
+        let _ = self.app.emit(
+            "download_progress",
+            DownloadProgress {
+                app_id: self.app_id.clone(),
+                progress: self.percent(),
+                downloaded: self.downloaded,
+                total: self.total,
+                phase,
+                bytes_per_second,
+                eta_seconds,
+            },
+        );
+    }
+}
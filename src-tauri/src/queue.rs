@@ -0,0 +1,389 @@
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::artifacts::Artifact;
+use crate::commands;
+use crate::downloads::{self, DownloadJob, DownloadOutcome};
+use crate::registry;
+use crate::settings;
+
+const JOBS_FILE_NAME: &str = "jobs.json";
+
+// Finished jobs kept around for the completed listing
+const MAX_COMPLETED_JOBS: usize = 50;
+
+static QUEUE: Lazy<Mutex<DownloadQueue>> = Lazy::new(|| Mutex::new(DownloadQueue::default()));
+
+// Set once at startup; every job reports its progress through it
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Active,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub app_id: String,
+    pub artifact: Artifact,
+    // Higher runs first; jobs with the same priority run in the order they were queued
+    pub priority: i32,
+    // Install the app once the download finishes
+    pub install: bool,
+    pub state: JobState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    // Mirror that served the download
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    // Waiting and paused jobs, in the order they will start
+    pub queued: Vec<Job>,
+    pub active: Vec<Job>,
+    // Most recently finished first
+    #[serde(default)]
+    pub completed: Vec<Job>,
+}
+
+#[derive(Default)]
+struct DownloadQueue {
+    queued: Vec<Job>,
+    active: Vec<Job>,
+    completed: Vec<Job>,
+}
+
+impl DownloadQueue {
+    fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            queued: self.queued.clone(),
+            active: self.active.clone(),
+            completed: self.completed.clone(),
+        }
+    }
+
+    fn contains(&self, app_id: &str) -> bool {
+        self.queued
+            .iter()
+            .chain(self.active.iter())
+            .any(|job| job.app_id == app_id)
+    }
+
+    // Keep the queue ordered by priority without reordering equal priorities
+    fn insert_queued(&mut self, job: Job) {
+        let index = self
+            .queued
+            .iter()
+            .position(|queued| queued.priority < job.priority)
+            .unwrap_or(self.queued.len());
+        self.queued.insert(index, job);
+    }
+
+    fn push_completed(&mut self, job: Job) {
+        self.completed.insert(0, job);
+        self.completed.truncate(MAX_COMPLETED_JOBS);
+    }
+
+    // Persist unfinished jobs so they survive a restart, then tell the UI
+    fn changed(&self) -> QueueSnapshot {
+        let snapshot = self.snapshot();
+        if let Err(e) = write_jobs(&snapshot) {
+            eprintln!("Warning: {}", e);
+        }
+        if let Some(app) = APP_HANDLE.get() {
+            let _ = app.emit("download_queue", snapshot.clone());
+        }
+        snapshot
+    }
+}
+
+fn load_jobs() -> QueueSnapshot {
+    let Some(path) = settings::get_app_data_directory()
+        .ok()
+        .map(|dir| dir.join(JOBS_FILE_NAME))
+    else {
+        return QueueSnapshot::default();
+    };
+    let Ok(contents) = fs::read(&path) else {
+        return QueueSnapshot::default();
+    };
+    serde_json::from_slice(&contents).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Failed to parse {:?}, starting with an empty queue: {}",
+            path, e
+        );
+        QueueSnapshot::default()
+    })
+}
+
+fn write_jobs(snapshot: &QueueSnapshot) -> Result<(), String> {
+    let path = settings::get_app_data_directory()?.join(JOBS_FILE_NAME);
+    let unfinished = QueueSnapshot {
+        queued: snapshot.queued.clone(),
+        active: snapshot.active.clone(),
+        completed: vec![],
+    };
+    let contents = serde_json::to_vec_pretty(&unfinished)
+        .map_err(|e| format!("Failed to serialize jobs: {}", e))?;
+    settings::write_atomic(&path, &contents).map_err(|e| format!("Failed to write jobs: {}", e))
+}
+
+fn app_handle() -> Result<&'static tauri::AppHandle, String> {
+    APP_HANDLE
+        .get()
+        .ok_or_else(|| "Download queue is not ready yet".to_string())
+}
+
+// Restore the jobs left over from the last run and start them again. Jobs
+// that were downloading go back to the front of the queue and continue from
+// their partial files; paused jobs stay paused.
+pub fn init(app: tauri::AppHandle) {
+    if APP_HANDLE.set(app).is_err() {
+        return;
+    }
+
+    let saved = load_jobs();
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        for mut job in saved.active {
+            job.state = JobState::Queued;
+            queue.queued.push(job);
+        }
+        for job in saved.queued {
+            queue.queued.push(job);
+        }
+        queue
+            .queued
+            .sort_by_key(|job| std::cmp::Reverse(job.priority));
+    }
+    pump();
+}
+
+pub fn snapshot() -> QueueSnapshot {
+    QUEUE.lock().unwrap().snapshot()
+}
+
+// Add a download to the queue and start it if a slot is free
+pub fn enqueue(
+    app_id: &str,
+    artifact: Artifact,
+    priority: i32,
+    install: bool,
+) -> Result<QueueSnapshot, String> {
+    app_handle()?;
+    registry::validate_app_id(app_id)?;
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        if queue.contains(app_id) {
+            return Err(format!("{} is already queued", app_id));
+        }
+        queue.insert_queued(Job {
+            app_id: app_id.to_string(),
+            artifact,
+            priority,
+            install,
+            state: JobState::Queued,
+            file_path: None,
+            source_url: None,
+            error: None,
+        });
+    }
+    Ok(pump())
+}
+
+// Change the priority of a waiting job, moving it to its new place in the queue
+pub fn set_priority(app_id: &str, priority: i32) -> Result<QueueSnapshot, String> {
+    let mut queue = QUEUE.lock().unwrap();
+    let index = queue
+        .queued
+        .iter()
+        .position(|job| job.app_id == app_id)
+        .ok_or_else(|| format!("{} is not queued", app_id))?;
+    let mut job = queue.queued.remove(index);
+    job.priority = priority;
+    queue.insert_queued(job);
+    Ok(queue.changed())
+}
+
+// Move a waiting job to another place in the queue. It takes the priority of
+// the job it lands in front of, so later priority changes keep the new order.
+pub fn move_job(app_id: &str, position: usize) -> Result<QueueSnapshot, String> {
+    let mut queue = QUEUE.lock().unwrap();
+    let index = queue
+        .queued
+        .iter()
+        .position(|job| job.app_id == app_id)
+        .ok_or_else(|| format!("{} is not queued", app_id))?;
+    let mut job = queue.queued.remove(index);
+    let position = position.min(queue.queued.len());
+    if let Some(neighbour) = queue
+        .queued
+        .get(position)
+        .or_else(|| position.checked_sub(1).and_then(|i| queue.queued.get(i)))
+    {
+        job.priority = neighbour.priority;
+    }
+    queue.queued.insert(position, job);
+    Ok(queue.changed())
+}
+
+// Pause an active job; the download keeps its partial file
+pub fn pause(app_id: &str) -> Result<(), String> {
+    if !downloads::request_pause(app_id) {
+        return Err(format!("No active download for app: {}", app_id));
+    }
+    Ok(())
+}
+
+// Put a paused job back in line
+pub fn resume(app_id: &str) -> Result<QueueSnapshot, String> {
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        let job = queue
+            .queued
+            .iter_mut()
+            .find(|job| job.app_id == app_id && job.state == JobState::Paused)
+            .ok_or_else(|| format!("No paused download for app: {}", app_id))?;
+        job.state = JobState::Queued;
+    }
+    Ok(pump())
+}
+
+// Cancel a job wherever it is. Waiting jobs are dropped right away; active
+// ones stop through their cancellation token and finish as cancelled.
+pub fn cancel(app_id: &str) -> Result<(), String> {
+    let mut queue = QUEUE.lock().unwrap();
+    if let Some(index) = queue.queued.iter().position(|job| job.app_id == app_id) {
+        let mut job = queue.queued.remove(index);
+        job.state = JobState::Cancelled;
+        queue.push_completed(job);
+        queue.changed();
+
+        if let Some(app) = APP_HANDLE.get() {
+            downloads::emit_cancelled(app, app_id);
+        }
+        return Ok(());
+    }
+    drop(queue);
+
+    if !downloads::request_cancel(app_id) {
+        return Err(format!("No active download for app: {}", app_id));
+    }
+    Ok(())
+}
+
+// Start waiting jobs while there are free slots, e.g. after a job finished
+// or the concurrency limit was raised
+pub fn pump() -> QueueSnapshot {
+    let max_active = settings::current_settings()
+        .downloads
+        .max_concurrent_downloads
+        .max(1);
+
+    let mut queue = QUEUE.lock().unwrap();
+    let mut started = vec![];
+    while queue.active.len() < max_active {
+        let Some(index) = queue
+            .queued
+            .iter()
+            .position(|job| job.state == JobState::Queued)
+        else {
+            break;
+        };
+        let mut job = queue.queued.remove(index);
+        // Register the job right away so it can be cancelled before its task runs
+        match DownloadJob::start(&job.app_id) {
+            Ok(control) => {
+                job.state = JobState::Active;
+                queue.active.push(job.clone());
+                started.push((job, control));
+            }
+            Err(e) => {
+                job.state = JobState::Failed;
+                job.error = Some(e);
+                queue.push_completed(job);
+            }
+        }
+    }
+
+    let snapshot = queue.changed();
+    drop(queue);
+
+    for (job, control) in started {
+        tauri::async_runtime::spawn(run_job(job, control));
+    }
+    snapshot
+}
+
+async fn run_job(mut job: Job, control: DownloadJob) {
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    let outcome = downloads::download_artifact(&control, &job.artifact, app).await;
+    // Installing cannot be interrupted, so the job stops accepting cancels here
+    let cancelled = control.finish();
+    match outcome {
+        Ok(DownloadOutcome::Completed { file_path, url }) => {
+            job.state = JobState::Completed;
+            job.file_path = Some(file_path.to_string_lossy().to_string());
+            job.source_url = Some(url);
+        }
+        Ok(DownloadOutcome::Cancelled) => job.state = JobState::Cancelled,
+        // Cancelled after the download had already stopped for the pause
+        Ok(DownloadOutcome::Paused) if cancelled => {
+            job.state = JobState::Cancelled;
+            downloads::emit_cancelled(app, &job.app_id);
+        }
+        Ok(DownloadOutcome::Paused) => job.state = JobState::Paused,
+        Err(e) => {
+            job.state = JobState::Failed;
+            job.error = Some(e);
+        }
+    }
+
+    // A job cancelled as its download finished is not installed
+    if job.install && job.state == JobState::Completed && cancelled {
+        job.state = JobState::Cancelled;
+        downloads::emit_cancelled(app, &job.app_id);
+    }
+    if job.install && job.state == JobState::Completed {
+        let file_path = job.file_path.clone().unwrap_or_default();
+        if let Err(e) = commands::install_app(job.app_id.clone(), file_path, app.clone()).await {
+            job.state = JobState::Failed;
+            job.error = Some(e);
+        }
+    }
+
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        queue.active.retain(|active| active.app_id != job.app_id);
+        if job.state == JobState::Paused {
+            // Paused jobs wait at the front of their priority until resumed
+            let index = queue
+                .queued
+                .iter()
+                .position(|queued| queued.priority <= job.priority)
+                .unwrap_or(queue.queued.len());
+            queue.queued.insert(index, job.clone());
+        } else {
+            queue.push_completed(job.clone());
+        }
+    }
+
+    pump();
+}
@@ -0,0 +1,525 @@
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::commands::App;
+use crate::fetcher::RegistryFetcher;
+use crate::registry_cache;
+use crate::signing;
+use crate::trust::{self, SnapshotMetadata};
+
+// Registry layout, relative to the source URL
+const INDEX_V1_PATH: &str = "index.json";
+const INDEX_V2_PATH: &str = "index.v2.json";
+
+// v2 index URLs found missing and when, so v1 registries are not asked for
+// one on every load. Entries expire so a registry that adds one is noticed.
+static MISSING_V2_INDEXES: Lazy<Mutex<HashMap<String, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+const MISSING_V2_INDEX_TTL: Duration = Duration::from_secs(60 * 60);
+
+fn manifest_path(app_id: &str) -> String {
+    format!("apps/{}.json", app_id)
+}
+
+// Public registry shipped as the default source
+pub const OFFICIAL_REGISTRY_ID: &str = "official";
+const APPS_REGISTRY_BASE_URL: &str =
+    "https://raw.githubusercontent.com/Nandanrmenon/fossintosh-repo/main";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrySource {
+    pub id: String,
+    pub name: String,
+    // Base URL containing index.v2.json, or index.json and apps/<id>.json.
+    // Supports https://, http:// and file:// locations.
+    pub url: String,
+    // Higher priority sources win when the same app id appears in several registries
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // Pinned minisign public key. When set, every index and manifest must come
+    // with a valid detached `.minisig` signature made by this key, and the
+    // registry must publish a signed snapshot.json (see trust.rs).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl RegistrySource {
+    pub fn official() -> Self {
+        RegistrySource {
+            id: OFFICIAL_REGISTRY_ID.to_string(),
+            name: "Fossintosh".to_string(),
+            url: APPS_REGISTRY_BASE_URL.to_string(),
+            priority: 0,
+            enabled: true,
+            public_key: None,
+        }
+    }
+
+    pub fn resource_url(&self, path: &str) -> String {
+        format!("{}/{}", self.url.trim_end_matches('/'), path)
+    }
+}
+
+// App ids end up in file names and temp paths, so they must not be able to
+// name another directory
+pub fn validate_app_id(app_id: &str) -> Result<(), String> {
+    let valid = !app_id.is_empty()
+        && !app_id.starts_with('.')
+        && app_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid app id: {:?}", app_id))
+    }
+}
+
+pub fn validate_sources(sources: &[RegistrySource]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for source in sources {
+        if source.id.trim().is_empty() {
+            return Err("Registry id must not be empty".to_string());
+        }
+        if !seen.insert(source.id.as_str()) {
+            return Err(format!("Duplicate registry id: {}", source.id));
+        }
+
+        let url = reqwest::Url::parse(&source.url)
+            .map_err(|e| format!("Invalid URL for registry {}: {}", source.id, e))?;
+        match url.scheme() {
+            "https" | "http" | "file" => {}
+            scheme => {
+                return Err(format!(
+                    "Unsupported URL scheme for registry {}: {}",
+                    source.id, scheme
+                ))
+            }
+        }
+
+        if let Some(public_key) = &source.public_key {
+            signing::parse_public_key(public_key)
+                .map_err(|e| format!("Registry {}: {}", source.id, e))?;
+        }
+    }
+    Ok(())
+}
+
+// Enabled sources ordered from highest to lowest priority. Ties keep the
+// order in which the sources were configured so the result is deterministic.
+pub fn ordered_sources(sources: &[RegistrySource]) -> Vec<RegistrySource> {
+    let mut enabled: Vec<RegistrySource> = sources.iter().filter(|s| s.enabled).cloned().collect();
+    enabled.sort_by_key(|s| std::cmp::Reverse(s.priority));
+    enabled
+}
+
+// Where fetching a registry resource went wrong
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStage {
+    // Connection, transport or filesystem failure
+    Network,
+    // The server answered with a non-success status
+    HttpStatus,
+    // The body was not valid JSON or did not match the manifest schema
+    Parse,
+    // The detached signature is missing or does not match the pinned key
+    Signature,
+    // The file does not match the hash recorded in the registry snapshot
+    Integrity,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    pub stage: FetchStage,
+    pub message: String,
+    // The resource does not exist (HTTP 404 or a missing local file)
+    pub not_found: bool,
+    // The server answered HTTP 403
+    pub forbidden: bool,
+}
+
+impl FetchError {
+    pub fn new(stage: FetchStage, message: String) -> Self {
+        FetchError {
+            stage,
+            message,
+            not_found: false,
+            forbidden: false,
+        }
+    }
+
+    pub fn not_found(message: String) -> Self {
+        FetchError {
+            stage: FetchStage::HttpStatus,
+            message,
+            not_found: true,
+            forbidden: false,
+        }
+    }
+
+    pub fn forbidden(message: String) -> Self {
+        FetchError {
+            stage: FetchStage::HttpStatus,
+            message,
+            not_found: false,
+            forbidden: true,
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// A manifest that could not be loaded, reported back so registry maintainers can fix it
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestFailure {
+    pub source: String,
+    pub id: String,
+    pub url: String,
+    pub stage: FetchStage,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceResult {
+    pub apps: Vec<App>,
+    pub failures: Vec<ManifestFailure>,
+}
+
+// Read a registry resource from either the network or the local filesystem
+pub async fn read_resource(fetcher: &RegistryFetcher, url: &str) -> Result<Vec<u8>, FetchError> {
+    if url.starts_with("file://") {
+        let path = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.to_file_path().ok())
+            .ok_or_else(|| {
+                FetchError::new(FetchStage::Network, format!("Invalid file URL: {}", url))
+            })?;
+        return tokio::fs::read(&path).await.map_err(|e| {
+            let message = format!("Failed to read {}: {}", path.display(), e);
+            if e.kind() == std::io::ErrorKind::NotFound {
+                FetchError::not_found(message)
+            } else {
+                FetchError::new(FetchStage::Network, message)
+            }
+        });
+    }
+
+    registry_cache::fetch_cached(fetcher, url).await
+}
+
+// Reads files from one source, checking signatures and snapshot hashes
+// whenever the source pins a public key
+struct SourceReader<'a> {
+    fetcher: &'a RegistryFetcher,
+    source: &'a RegistrySource,
+    snapshot: Option<SnapshotMetadata>,
+}
+
+impl SourceReader<'_> {
+    async fn read(&self, path: &str) -> Result<Vec<u8>, FetchError> {
+        let url = self.source.resource_url(path);
+        let bytes = read_resource(self.fetcher, &url).await?;
+
+        let Some(public_key) = &self.source.public_key else {
+            return Ok(bytes);
+        };
+
+        let signature = match read_resource(self.fetcher, &signing::signature_url(&url)).await {
+            Ok(signature) => signature,
+            Err(e) if e.not_found => {
+                return Err(FetchError::new(
+                    FetchStage::Signature,
+                    format!("Refusing unsigned metadata {}", url),
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+
+        signing::verify_signature(public_key, &bytes, &signature)
+            .map_err(|e| FetchError::new(FetchStage::Signature, format!("{}: {}", url, e)))?;
+
+        if let Some(snapshot) = &self.snapshot {
+            trust::check_file(snapshot, path, &bytes)
+                .map_err(|e| FetchError::new(FetchStage::Integrity, e))?;
+        }
+
+        Ok(bytes)
+    }
+
+    // Load the signed snapshot and refuse expired or rolled back metadata
+    async fn load_snapshot(&mut self) -> Result<(), String> {
+        if self.source.public_key.is_none() {
+            return Ok(());
+        }
+
+        let bytes = self
+            .read(trust::SNAPSHOT_PATH)
+            .await
+            .map_err(|e| format!("Failed to fetch registry snapshot: {}", e))?;
+        let snapshot: SnapshotMetadata =
+            parse_json(&bytes).map_err(|e| format!("Failed to parse registry snapshot: {}", e))?;
+        trust::check_snapshot(&self.source.id, &snapshot)?;
+
+        self.snapshot = Some(snapshot);
+        Ok(())
+    }
+}
+
+// Read any other file published by a source, with the same signature and
+// snapshot checks as the index
+pub async fn fetch_source_file(
+    fetcher: &RegistryFetcher,
+    source: &RegistrySource,
+    path: &str,
+) -> Result<Vec<u8>, FetchError> {
+    let mut reader = SourceReader {
+        fetcher,
+        source,
+        snapshot: None,
+    };
+    reader
+        .load_snapshot()
+        .await
+        .map_err(|e| FetchError::new(FetchStage::Integrity, e))?;
+    reader.read(path).await
+}
+
+fn path_error<E: std::fmt::Display>(e: serde_path_to_error::Error<E>) -> FetchError {
+    let path = e.path().to_string();
+    let message = if path == "." {
+        e.inner().to_string()
+    } else {
+        format!("{} (at {})", e.inner(), path)
+    };
+    FetchError::new(FetchStage::Parse, message)
+}
+
+// Deserialize JSON, reporting the path of the offending field on failure
+pub fn parse_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, FetchError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(bytes);
+    serde_path_to_error::deserialize(deserializer).map_err(path_error)
+}
+
+fn parse_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, FetchError> {
+    serde_path_to_error::deserialize(value).map_err(path_error)
+}
+
+// Registry indexes come in two layouts:
+// v1: a JSON array of app ids, each manifest lives at apps/<id>.json
+// v2: {"schemaVersion": 2, "apps": [...]} with every manifest embedded
+enum RegistryIndex {
+    V1(Vec<String>),
+    V2(Vec<serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexV2 {
+    schema_version: u32,
+    apps: Vec<serde_json::Value>,
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// Indexes may be published gzip or zstd compressed; detect it from the content
+fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, FetchError> {
+    use std::io::Read;
+
+    let mut decoded = vec![];
+    if bytes.starts_with(GZIP_MAGIC) {
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .map_err(|e| FetchError::new(FetchStage::Parse, format!("Invalid gzip data: {}", e)))?;
+    } else if bytes.starts_with(ZSTD_MAGIC) {
+        decoded = zstd::stream::decode_all(bytes.as_slice())
+            .map_err(|e| FetchError::new(FetchStage::Parse, format!("Invalid zstd data: {}", e)))?;
+    } else {
+        return Ok(bytes);
+    }
+    Ok(decoded)
+}
+
+fn parse_index(bytes: Vec<u8>) -> Result<RegistryIndex, FetchError> {
+    let bytes = decompress(bytes)?;
+    let value: serde_json::Value = parse_json(&bytes)?;
+
+    if value.is_array() {
+        return parse_value(value).map(RegistryIndex::V1);
+    }
+
+    let index: IndexV2 = parse_value(value)?;
+    if index.schema_version != 2 {
+        return Err(FetchError::new(
+            FetchStage::Parse,
+            format!(
+                "Unsupported registry index schema version: {}",
+                index.schema_version
+            ),
+        ));
+    }
+    Ok(RegistryIndex::V2(index.apps))
+}
+
+// Prefer the single-file v2 index and fall back to the v1 layout when the
+// registry does not publish one
+async fn fetch_index(reader: &SourceReader<'_>) -> Result<(String, RegistryIndex), FetchError> {
+    let v2_url = reader.source.resource_url(INDEX_V2_PATH);
+    let known_missing = MISSING_V2_INDEXES
+        .lock()
+        .unwrap()
+        .get(&v2_url)
+        .is_some_and(|checked_at| checked_at.elapsed() < MISSING_V2_INDEX_TTL);
+    if !known_missing {
+        match reader.read(INDEX_V2_PATH).await {
+            Ok(bytes) => return Ok((INDEX_V2_PATH.to_string(), parse_index(bytes)?)),
+            // S3 and CloudFront style hosts answer 403 for keys that do not
+            // exist, so only here a 403 also means there is no v2 index
+            Err(e) if e.not_found || e.forbidden => {
+                MISSING_V2_INDEXES
+                    .lock()
+                    .unwrap()
+                    .insert(v2_url, Instant::now());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let bytes = reader.read(INDEX_V1_PATH).await?;
+    Ok((INDEX_V1_PATH.to_string(), parse_index(bytes)?))
+}
+
+// Probe every registry for a v2 index again on the next load
+pub fn forget_missing_indexes() {
+    MISSING_V2_INDEXES.lock().unwrap().clear();
+}
+
+async fn fetch_manifest(reader: &SourceReader<'_>, path: &str) -> Result<App, FetchError> {
+    let bytes = reader.read(path).await?;
+    parse_json(&bytes)
+}
+
+// Fetch every app listed by a single registry source. Only a broken index
+// fails the whole source; individual manifest errors are collected instead.
+pub async fn fetch_source(
+    fetcher: &RegistryFetcher,
+    source: &RegistrySource,
+) -> Result<SourceResult, String> {
+    let mut reader = SourceReader {
+        fetcher,
+        source,
+        snapshot: None,
+    };
+    reader.load_snapshot().await?;
+
+    let (index_path, index) = fetch_index(&reader).await.map_err(|e| match e.stage {
+        FetchStage::Parse => format!("Failed to parse registry index: {}", e),
+        FetchStage::Signature | FetchStage::Integrity => {
+            format!("Failed to verify registry index: {}", e)
+        }
+        _ => format!("Failed to fetch registry index: {}", e),
+    })?;
+
+    let app_ids = match index {
+        RegistryIndex::V1(app_ids) => app_ids,
+        RegistryIndex::V2(manifests) => {
+            let index_url = source.resource_url(&index_path);
+            return Ok(parse_embedded_manifests(source, &index_url, manifests));
+        }
+    };
+
+    // Fetch each app in parallel; the fetcher caps how many requests are in flight
+    let reader = &reader;
+    let mut app_futures = vec![];
+    for app_id in app_ids {
+        let path = manifest_path(&app_id);
+        let future = async move {
+            let result = fetch_manifest(reader, &path).await;
+            (app_id, source.resource_url(&path), result)
+        };
+        app_futures.push(future);
+    }
+
+    let mut result = SourceResult::default();
+    for (app_id, url, manifest) in futures_util::future::join_all(app_futures).await {
+        match manifest {
+            Ok(mut app) => {
+                app.source = Some(source.id.clone());
+                result.apps.push(app);
+            }
+            Err(e) => {
+                eprintln!("Failed to load manifest {}: {}", url, e);
+                result.failures.push(ManifestFailure {
+                    source: source.id.clone(),
+                    id: app_id,
+                    url,
+                    stage: e.stage,
+                    error: e.message,
+                });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn parse_embedded_manifests(
+    source: &RegistrySource,
+    index_url: &str,
+    manifests: Vec<serde_json::Value>,
+) -> SourceResult {
+    let mut result = SourceResult::default();
+    for (position, manifest) in manifests.into_iter().enumerate() {
+        let app_id = manifest
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| format!("#{}", position));
+
+        match parse_value::<App>(manifest) {
+            Ok(mut app) => {
+                app.source = Some(source.id.clone());
+                result.apps.push(app);
+            }
+            Err(e) => {
+                eprintln!("Failed to load embedded manifest {}: {}", app_id, e);
+                result.failures.push(ManifestFailure {
+                    source: source.id.clone(),
+                    id: app_id,
+                    url: format!("{}#/apps/{}", index_url, position),
+                    stage: e.stage,
+                    error: e.message,
+                });
+            }
+        }
+    }
+    result
+}
+
+// Merge per-source results, which must already be ordered by priority.
+// The first source providing an app id wins; later duplicates are dropped.
+pub fn merge_sources(results: Vec<Vec<App>>) -> Vec<App> {
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .flatten()
+        .filter(|app| seen.insert(app.id.clone()))
+        .collect()
+}
@@ -0,0 +1,161 @@
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fetcher::RegistryFetcher;
+use crate::registry::{FetchError, FetchStage};
+use crate::settings::write_atomic;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CacheMetadata {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // Unix timestamp (seconds) of the last successful fetch or revalidation
+    fetched_at: u64,
+}
+
+pub fn get_cache_directory() -> Result<PathBuf, String> {
+    let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
+    let cache_dir = PathBuf::from(home)
+        .join("Library")
+        .join("Caches")
+        .join("dev.nandanmenon.fossintosh")
+        .join("registry");
+
+    fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    Ok(cache_dir)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Cache entries are keyed by a hash of the URL so any URL maps to a safe file name
+fn cache_paths(url: &str) -> Result<(PathBuf, PathBuf), String> {
+    let key = hex::encode(Sha256::digest(url.as_bytes()));
+    let dir = get_cache_directory()?;
+    Ok((
+        dir.join(format!("{}.meta.json", key)),
+        dir.join(format!("{}.body", key)),
+    ))
+}
+
+fn load_entry(url: &str) -> Option<(CacheMetadata, Vec<u8>)> {
+    let (meta_path, body_path) = cache_paths(url).ok()?;
+    let metadata: CacheMetadata = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+    // Guard against hash collisions or a hand-edited cache directory
+    if metadata.url != url {
+        return None;
+    }
+    let body = fs::read(body_path).ok()?;
+    Some((metadata, body))
+}
+
+fn store_entry(metadata: &CacheMetadata, body: Option<&[u8]>) -> Result<(), String> {
+    let (meta_path, body_path) = cache_paths(&metadata.url)?;
+    if let Some(body) = body {
+        write_atomic(&body_path, body)
+            .map_err(|e| format!("Failed to write cache entry: {}", e))?;
+    }
+    let meta = serde_json::to_vec(metadata)
+        .map_err(|e| format!("Failed to serialize cache metadata: {}", e))?;
+    write_atomic(&meta_path, &meta).map_err(|e| format!("Failed to write cache metadata: {}", e))
+}
+
+// Fetch a URL through the on-disk cache. A cached copy is revalidated with
+// If-None-Match / If-Modified-Since and reused when the server answers 304.
+pub async fn fetch_cached(fetcher: &RegistryFetcher, url: &str) -> Result<Vec<u8>, FetchError> {
+    let cached = load_entry(url);
+    let validators = cached
+        .as_ref()
+        .map(|(metadata, _)| (metadata.etag.clone(), metadata.last_modified.clone()));
+
+    let response = fetcher
+        .fetch(url, |client| {
+            let mut request = client.get(url);
+            if let Some((etag, last_modified)) = &validators {
+                if let Some(etag) = etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            request
+        })
+        .await?;
+
+    if response.status == StatusCode::NOT_MODIFIED {
+        if let Some((mut metadata, body)) = cached {
+            metadata.fetched_at = unix_now();
+            if let Err(e) = store_entry(&metadata, None) {
+                eprintln!("Warning: {}", e);
+            }
+            return Ok(body);
+        }
+        return Err(FetchError::new(
+            FetchStage::HttpStatus,
+            format!("Failed to fetch {}: got 304 without a cached copy", url),
+        ));
+    }
+
+    if response.status == StatusCode::NOT_FOUND {
+        return Err(FetchError::not_found(format!(
+            "Failed to fetch {}: HTTP {}",
+            url, response.status
+        )));
+    }
+
+    if response.status == StatusCode::FORBIDDEN {
+        return Err(FetchError::forbidden(format!(
+            "Failed to fetch {}: HTTP {}",
+            url, response.status
+        )));
+    }
+
+    if !response.status.is_success() {
+        return Err(FetchError::new(
+            FetchStage::HttpStatus,
+            format!("Failed to fetch {}: HTTP {}", url, response.status),
+        ));
+    }
+
+    let header_value = |name| {
+        response
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let metadata = CacheMetadata {
+        url: url.to_string(),
+        etag: header_value(ETAG),
+        last_modified: header_value(LAST_MODIFIED),
+        fetched_at: unix_now(),
+    };
+
+    // Responses without validators can never be revalidated, so don't keep them
+    if metadata.etag.is_some() || metadata.last_modified.is_some() {
+        if let Err(e) = store_entry(&metadata, Some(&response.body)) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    Ok(response.body)
+}
+
+pub fn clear() -> Result<(), String> {
+    let dir = get_cache_directory()?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear registry cache: {}", e))
+}
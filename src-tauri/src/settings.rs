@@ -0,0 +1,104 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::downloads::DownloadSettings;
+use crate::fetcher::RegistryFetchSettings;
+use crate::http::NetworkSettings;
+use crate::registry::RegistrySource;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+// Loaded once from disk, then kept in memory and written back on every change
+static SETTINGS: Lazy<RwLock<Settings>> = Lazy::new(|| RwLock::new(load_settings()));
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    #[serde(default = "default_registries")]
+    pub registries: Vec<RegistrySource>,
+    #[serde(default)]
+    pub registry_fetch: RegistryFetchSettings,
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    // Proxy, certificates and User-Agent for all requests
+    #[serde(default)]
+    pub network: NetworkSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            registries: default_registries(),
+            registry_fetch: RegistryFetchSettings::default(),
+            downloads: DownloadSettings::default(),
+            network: NetworkSettings::default(),
+        }
+    }
+}
+
+fn default_registries() -> Vec<RegistrySource> {
+    vec![RegistrySource::official()]
+}
+
+// Write via a temporary file and rename so a crash or quit never leaves a
+// torn file behind
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!("{}.tmp", file_name));
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn get_app_data_directory() -> Result<PathBuf, String> {
+    let home = std::env::var("HOME").map_err(|e| format!("Failed to get HOME: {}", e))?;
+    let data_dir = PathBuf::from(home)
+        .join("Library")
+        .join("Application Support")
+        .join("dev.nandanmenon.fossintosh");
+
+    fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    Ok(data_dir)
+}
+
+fn load_settings() -> Settings {
+    let path = match get_app_data_directory() {
+        Ok(dir) => dir.join(SETTINGS_FILE_NAME),
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            return Settings::default();
+        }
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to parse {:?}, using defaults: {}", path, e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+fn write_settings(settings: &Settings) -> Result<(), String> {
+    let path = get_app_data_directory()?.join(SETTINGS_FILE_NAME);
+    let contents = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    write_atomic(&path, contents.as_bytes()).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+pub fn current_settings() -> Settings {
+    SETTINGS.read().unwrap().clone()
+}
+
+pub fn save_settings(settings: Settings) -> Result<Settings, String> {
+    write_settings(&settings)?;
+    *SETTINGS.write().unwrap() = settings.clone();
+    Ok(settings)
+}
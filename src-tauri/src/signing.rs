@@ -0,0 +1,30 @@
+use minisign_verify::{PublicKey, Signature};
+
+// Detached minisign signatures are published next to the signed file
+pub fn signature_url(url: &str) -> String {
+    format!("{}.minisig", url)
+}
+
+// Accepts either the bare base64 key or the full contents of a minisign.pub file
+pub fn parse_public_key(public_key: &str) -> Result<PublicKey, String> {
+    let public_key = public_key.trim();
+    let parsed = if public_key.lines().count() > 1 {
+        PublicKey::decode(public_key)
+    } else {
+        PublicKey::from_base64(public_key)
+    };
+    parsed.map_err(|e| format!("Invalid minisign public key: {}", e))
+}
+
+pub fn verify_signature(public_key: &str, data: &[u8], signature: &[u8]) -> Result<(), String> {
+    let public_key = parse_public_key(public_key)?;
+    let signature = std::str::from_utf8(signature)
+        .map_err(|_| "Signature is not valid UTF-8".to_string())
+        .and_then(|s| {
+            Signature::decode(s).map_err(|e| format!("Invalid minisign signature: {}", e))
+        })?;
+
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| format!("Signature verification failed: {}", e))
+}
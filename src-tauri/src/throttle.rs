@@ -0,0 +1,79 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::downloads::DownloadSettings;
+use crate::settings;
+
+// Current limits in bytes per second, 0 meaning unlimited. Read on every
+// chunk so changed settings apply to downloads that are already running.
+static GLOBAL_LIMIT: Lazy<AtomicU64> =
+    Lazy::new(|| AtomicU64::new(settings::current_settings().downloads.max_bytes_per_second));
+static PER_DOWNLOAD_LIMIT: Lazy<AtomicU64> = Lazy::new(|| {
+    AtomicU64::new(
+        settings::current_settings()
+            .downloads
+            .per_download_bytes_per_second,
+    )
+});
+
+// Shared by all downloads
+static GLOBAL_BUCKET: Lazy<Mutex<TokenBucket>> = Lazy::new(|| Mutex::new(TokenBucket::new()));
+
+pub fn configure(settings: &DownloadSettings) {
+    GLOBAL_LIMIT.store(settings.max_bytes_per_second, Ordering::SeqCst);
+    PER_DOWNLOAD_LIMIT.store(settings.per_download_bytes_per_second, Ordering::SeqCst);
+}
+
+// Token bucket holding at most one second worth of bytes. Taking more than is
+// available leaves the bucket in debt, and the caller waits until it is repaid.
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Default for TokenBucket {
+    fn default() -> Self {
+        TokenBucket {
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+}
+
+impl TokenBucket {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn take(&mut self, bytes: u64, rate: u64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+
+        if rate == 0 {
+            self.tokens = 0.0;
+            return Duration::ZERO;
+        }
+
+        let rate = rate as f64;
+        self.tokens = (self.tokens + elapsed * rate).min(rate) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+// How long to wait after receiving `bytes` to stay within both the download's
+// own limit and the global one
+pub fn delay_for(bucket: &mut TokenBucket, bytes: u64) -> Duration {
+    let own = bucket.take(bytes, PER_DOWNLOAD_LIMIT.load(Ordering::SeqCst));
+    let global = GLOBAL_BUCKET
+        .lock()
+        .unwrap()
+        .take(bytes, GLOBAL_LIMIT.load(Ordering::SeqCst));
+    own.max(global)
+}
@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use crate::settings;
+
+const TRUST_FILE_NAME: &str = "trust.json";
+
+// Registry file listing the current metadata version, its expiry and the hash
+// of every other metadata file. Signed like the rest of the registry.
+pub const SNAPSHOT_PATH: &str = "snapshot.json";
+
+// Highest snapshot version accepted so far for every source, or why the
+// stored versions could not be read
+static TRUSTED_VERSIONS: Lazy<Mutex<Result<HashMap<String, u64>, String>>> =
+    Lazy::new(|| Mutex::new(load_trusted_versions()));
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SnapshotMetadata {
+    pub version: u64,
+    pub expires: DateTime<Utc>,
+    // Paths relative to the registry root, e.g. "index.json" or "apps/foo.json"
+    pub meta: HashMap<String, FileMeta>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FileMeta {
+    pub sha256: String,
+    #[serde(default)]
+    pub length: Option<u64>,
+}
+
+// Only a missing file starts from scratch. Anything unreadable fails closed,
+// since treating it as empty would accept any rolled back registry.
+fn load_trusted_versions() -> Result<HashMap<String, u64>, String> {
+    let path = settings::get_app_data_directory()?.join(TRUST_FILE_NAME);
+    let contents = match fs::read(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("Failed to read trusted versions {:?}: {}", path, e)),
+    };
+    serde_json::from_slice(&contents).map_err(|e| {
+        format!(
+            "Failed to parse trusted versions {:?}: {}; delete it to trust the registries again",
+            path, e
+        )
+    })
+}
+
+fn write_trusted_versions(versions: &HashMap<String, u64>) -> Result<(), String> {
+    let path = settings::get_app_data_directory()?.join(TRUST_FILE_NAME);
+    let contents = serde_json::to_vec_pretty(versions)
+        .map_err(|e| format!("Failed to serialize trusted versions: {}", e))?;
+    settings::write_atomic(&path, &contents)
+        .map_err(|e| format!("Failed to write trusted versions: {}", e))
+}
+
+// Reject expired snapshots (freeze attack) and snapshots older than one we
+// already accepted (rollback attack), then remember the new version
+pub fn check_snapshot(source_id: &str, snapshot: &SnapshotMetadata) -> Result<(), String> {
+    let mut versions = TRUSTED_VERSIONS.lock().unwrap();
+    let versions = versions.as_mut().map_err(|e| e.clone())?;
+    if accept_snapshot(versions, source_id, snapshot, Utc::now())? {
+        write_trusted_versions(versions)?;
+    }
+    Ok(())
+}
+
+// The checks behind check_snapshot, against the given trusted versions.
+// Returns whether the version of the source was raised.
+fn accept_snapshot(
+    versions: &mut HashMap<String, u64>,
+    source_id: &str,
+    snapshot: &SnapshotMetadata,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    if snapshot.expires <= now {
+        return Err(format!(
+            "Registry metadata expired at {}; the registry may be serving stale data",
+            snapshot.expires.to_rfc3339()
+        ));
+    }
+
+    let trusted = versions.get(source_id).copied().unwrap_or(0);
+    if snapshot.version < trusted {
+        return Err(format!(
+            "Registry snapshot version {} is older than the trusted version {}; refusing rollback",
+            snapshot.version, trusted
+        ));
+    }
+
+    if snapshot.version > trusted {
+        versions.insert(source_id.to_string(), snapshot.version);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// Every metadata file must be listed in the snapshot with a matching hash,
+// which stops an older but validly signed file from being replayed
+pub fn check_file(snapshot: &SnapshotMetadata, path: &str, bytes: &[u8]) -> Result<(), String> {
+    let expected = snapshot
+        .meta
+        .get(path)
+        .ok_or_else(|| format!("{} is not listed in the registry snapshot", path))?;
+
+    if let Some(length) = expected.length {
+        if length != bytes.len() as u64 {
+            return Err(format!(
+                "{} is {} bytes but the registry snapshot expects {}",
+                path,
+                bytes.len(),
+                length
+            ));
+        }
+    }
+
+    let actual = hex::encode(Sha256::digest(bytes));
+    if !actual.eq_ignore_ascii_case(&expected.sha256) {
+        return Err(format!(
+            "{} does not match the hash in the registry snapshot",
+            path
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const INDEX: &[u8] = b"{\"apps\": []}";
+
+    fn snapshot(version: u64, expires: DateTime<Utc>) -> SnapshotMetadata {
+        SnapshotMetadata {
+            version,
+            expires,
+            meta: HashMap::from([(
+                "index.json".to_string(),
+                FileMeta {
+                    sha256: hex::encode(Sha256::digest(INDEX)),
+                    length: Some(INDEX.len() as u64),
+                },
+            )]),
+        }
+    }
+
+    fn trusted(version: u64) -> HashMap<String, u64> {
+        HashMap::from([("official".to_string(), version)])
+    }
+
+    #[test]
+    fn refuses_expired_snapshot() {
+        let now = Utc::now();
+        let mut versions = trusted(1);
+        let error = accept_snapshot(&mut versions, "official", &snapshot(2, now), now).unwrap_err();
+        assert!(error.contains("expired"), "{}", error);
+        assert_eq!(versions, trusted(1));
+    }
+
+    #[test]
+    fn refuses_rollback() {
+        let now = Utc::now();
+        let mut versions = trusted(5);
+        let error = accept_snapshot(
+            &mut versions,
+            "official",
+            &snapshot(4, now + Duration::days(1)),
+            now,
+        )
+        .unwrap_err();
+        assert!(error.contains("refusing rollback"), "{}", error);
+        assert_eq!(versions, trusted(5));
+    }
+
+    #[test]
+    fn accepts_same_version_without_change() {
+        let now = Utc::now();
+        let mut versions = trusted(5);
+        let changed = accept_snapshot(
+            &mut versions,
+            "official",
+            &snapshot(5, now + Duration::days(1)),
+            now,
+        );
+        assert_eq!(changed, Ok(false));
+        assert_eq!(versions, trusted(5));
+    }
+
+    #[test]
+    fn remembers_newer_version() {
+        let now = Utc::now();
+        let mut versions = HashMap::new();
+        let changed = accept_snapshot(
+            &mut versions,
+            "official",
+            &snapshot(3, now + Duration::days(1)),
+            now,
+        );
+        assert_eq!(changed, Ok(true));
+        assert_eq!(versions, trusted(3));
+    }
+
+    #[test]
+    fn refuses_unlisted_file() {
+        let snapshot = snapshot(1, Utc::now());
+        let error = check_file(&snapshot, "apps/foo.json", INDEX).unwrap_err();
+        assert!(error.contains("not listed"), "{}", error);
+    }
+
+    #[test]
+    fn refuses_length_mismatch() {
+        let snapshot = snapshot(1, Utc::now());
+        let error = check_file(&snapshot, "index.json", b"{\"apps\": [ ]}").unwrap_err();
+        assert!(error.contains("bytes"), "{}", error);
+    }
+
+    #[test]
+    fn refuses_hash_mismatch() {
+        let snapshot = snapshot(1, Utc::now());
+        let error = check_file(&snapshot, "index.json", b"{\"apps\": {}}").unwrap_err();
+        assert!(error.contains("does not match"), "{}", error);
+    }
+
+    #[test]
+    fn matches_hash_case_insensitively() {
+        let mut snapshot = snapshot(1, Utc::now());
+        let meta = snapshot.meta.get_mut("index.json").unwrap();
+        meta.sha256 = meta.sha256.to_uppercase();
+        meta.length = None;
+        assert_eq!(check_file(&snapshot, "index.json", INDEX), Ok(()));
+    }
+}
@@ -1,8 +1,12 @@
 import { JSX, useEffect, useMemo, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { App } from "./types/app.types";
-import { DownloadState } from "./types/download.types";
+import type { App, Catalog, Category } from "./types/app.types";
+import {
+  DownloadProgressEvent,
+  DownloadState,
+  QueueSnapshot,
+} from "./types/download.types";
 import "./App.css";
 import { AppDetail } from "./pages/AppDetail";
 import { CategoriesPage } from "./pages/CategoriesPage";
@@ -13,19 +17,58 @@ import { Download, Library, Telescope } from "lucide-react";
 
 type MenuKey = "discover" | "categories" | "update";
 
+const formatBytes = (bytes: number) => {
+  const units = ["B", "KB", "MB", "GB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+};
+
+const formatDownloadStatus = (data: DownloadProgressEvent) => {
+  switch (data.phase) {
+    case "starting":
+      return "Starting download...";
+    case "verifying":
+      return "Verifying download...";
+    case "paused":
+      return "Paused";
+    case "downloading": {
+      const parts = [`Downloading: ${data.progress.toFixed(1)}%`];
+      if (data.bytes_per_second > 0) {
+        parts.push(`${formatBytes(data.bytes_per_second)}/s`);
+      }
+      if (data.eta_seconds != null) {
+        const minutes = Math.floor(data.eta_seconds / 60);
+        const seconds = data.eta_seconds % 60;
+        parts.push(minutes > 0 ? `${minutes}m ${seconds}s left` : `${seconds}s left`);
+      }
+      return parts.join(" · ");
+    }
+  }
+};
+
 function App() {
   const [apps, setApps] = useState<App[]>([]);
+  const [categoryList, setCategoryList] = useState<Category[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string>("");
   const [downloadStates, setDownloadStates] = useState<DownloadState>({});
   const [selectedApp, setSelectedApp] = useState<App | null>(null);
   const [activeMenu, setActiveMenu] = useState<MenuKey>("discover");
   const [searchTerm, setSearchTerm] = useState("");
+  const [selectedCategory, setSelectedCategory] = useState<Category | null>(
+    null,
+  );
   const searchInputRef = useRef<HTMLInputElement | null>(null);
 
   useEffect(() => {
     loadApps();
     setupEventListeners();
+    loadJobs();
   }, []);
 
   useEffect(() => {
@@ -56,6 +99,7 @@ function App() {
 
   const handleMenuSelect = (menu: MenuKey) => {
     setActiveMenu(menu);
+    setSelectedCategory(null);
     setSelectedApp(null);
   };
 
@@ -63,21 +107,16 @@ function App() {
     try {
       // Listen to download progress events
       await listen("download_progress", (event) => {
-        const data = event.payload as {
-          app_id: string;
-          progress: number;
-          downloaded: number;
-          total: number;
-          status: string;
-        };
+        const data = event.payload as DownloadProgressEvent;
 
         setDownloadStates((prev) => ({
           ...prev,
           [data.app_id]: {
             ...(prev[data.app_id] || {}),
-            isDownloading: true,
+            isDownloading: data.phase !== "paused",
+            isPaused: data.phase === "paused",
             progress: data.progress,
-            status: data.status,
+            status: formatDownloadStatus(data),
           },
         }));
       });
@@ -89,17 +128,36 @@ function App() {
           file_path: string;
           success: boolean;
           error?: string;
+          error_kind?:
+            | "retryable"
+            | "fatal"
+            | "cancelled"
+            | "stalled"
+            | "checksum_mismatch"
+            | null;
         };
 
+        const failedStatus =
+          data.error_kind === "cancelled"
+            ? "Download canceled"
+            : data.error_kind === "retryable"
+              ? "Download interrupted, try again later"
+              : data.error_kind === "stalled"
+                ? "Download stalled, try again later"
+                : data.error_kind === "checksum_mismatch"
+                  ? "Download corrupted: checksum does not match"
+                  : "Download failed";
+
         setDownloadStates((prev) => ({
           ...prev,
           [data.app_id]: {
             ...(prev[data.app_id] || {}),
             isDownloading: false,
+            isPaused: false,
             progress: data.success ? 100 : 0,
             status: data.success
-              ? `Downloaded to Downloads/${data.app_id}.dmg`
-              : "Download failed",
+              ? `Downloaded to Downloads/${data.file_path.split("/").pop()}`
+              : failedStatus,
             error: data.error,
             filePath: data.file_path,
             isDownloaded: data.success,
@@ -152,15 +210,57 @@ function App() {
     }
   };
 
+  // Rehydrate download state from jobs restored by the backend after a restart
+  const loadJobs = async () => {
+    try {
+      const jobs = await invoke<QueueSnapshot>("list_jobs");
+      setDownloadStates((prev) => {
+        const next = { ...prev };
+        for (const job of [...jobs.active, ...jobs.queued]) {
+          next[job.appId] = {
+            ...(next[job.appId] || {}),
+            isDownloading: job.state !== "paused",
+            isPaused: job.state === "paused",
+            status: job.state === "paused" ? "Paused" : "Queued",
+          };
+        }
+        return next;
+      });
+    } catch (error) {
+      console.error("Failed to load download jobs:", error);
+    }
+  };
+
   const loadApps = async () => {
     try {
       setLoading(true);
-      const fetchedApps = await invoke<App[]>("fetch_apps");
-      setApps(fetchedApps);
-      setError("");
+      const catalog = await invoke<Catalog>("fetch_apps");
+      setApps(catalog.apps);
+      if (catalog.failures.length > 0) {
+        console.warn("Some app manifests failed to load:", catalog.failures);
+      }
+      const messages = catalog.sourceErrors.map(
+        (sourceError) => `${sourceError.source}: ${sourceError.error}`,
+      );
+      if (catalog.stale) {
+        // The source errors below say why, e.g. offline, expired or rolled back
+        messages.unshift(
+          `${catalog.staleSources.join(", ")} could not be refreshed: showing apps saved ${new Date(catalog.fetchedAt * 1000).toLocaleString()}`,
+        );
+      }
+      setError(messages.join("\n"));
+
+      const fetchedCategories = await invoke<Category[]>("list_categories");
+      setCategoryList(fetchedCategories);
     } catch (error) {
       console.error("Failed to fetch apps:", error);
-      setError(error instanceof Error ? error.message : "Failed to load apps");
+      setError(
+        error instanceof Error
+          ? error.message
+          : typeof error === "string"
+            ? error
+            : "Failed to load apps",
+      );
     } finally {
       setLoading(false);
     }
@@ -289,26 +389,34 @@ function App() {
     );
   }, [apps, searchTerm]);
 
-  const categories = useMemo(() => {
-    const counts: Record<string, number> = {};
-
-    apps.forEach((app) => {
-      counts[app.category] = (counts[app.category] || 0) + 1;
-    });
-
-    return Object.entries(counts)
-      .map(([name, count]) => ({ name, count }))
-      .sort((a, b) => b.count - a.count || a.name.localeCompare(b.name));
-  }, [apps]);
+  const categoryApps = useMemo(() => {
+    if (!selectedCategory) return [] as App[];
+    const ids = new Set(selectedCategory.appIds);
+    return apps.filter((app) => ids.has(app.id));
+  }, [apps, selectedCategory]);
+
+  const categories = useMemo(
+    () =>
+      categoryList.map((category) => ({
+        id: category.id,
+        name: category.name,
+        count: category.appCount,
+      })),
+    [categoryList],
+  );
 
   const updateCount = useMemo(
     () => apps.filter((app) => Boolean(app.hasUpdate)).length,
     [apps],
   );
 
-  const handleCategorySelect = (category: string) => {
-    setSearchTerm(category);
-    setActiveMenu("discover");
+  // Filter on the apps the backend counted, not on the raw category text
+  const handleCategorySelect = (categoryId: string) => {
+    setSelectedCategory(
+      categoryList.find((category) => category.id === categoryId) ?? null,
+    );
+    setSearchTerm("");
+    setActiveMenu("categories");
     setSelectedApp(null);
   };
 
@@ -423,6 +531,17 @@ function App() {
                     onInstall={handleInstall}
                     onCardClick={handleCardClick}
                   />
+                ) : activeMenu === "categories" && selectedCategory ? (
+                  <SearchResultsPage
+                    apps={categoryApps}
+                    downloadStates={downloadStates}
+                    searchTerm={searchTerm}
+                    onSearchChange={setSearchTerm}
+                    onDownload={handleDownload}
+                    onCancelDownload={handleCancelDownload}
+                    onInstall={handleInstall}
+                    onCardClick={handleCardClick}
+                  />
                 ) : activeMenu === "categories" ? (
                   <CategoriesPage
                     categories={categories}
@@ -1,11 +1,12 @@
 interface CategoryItem {
+  id: string;
   name: string;
   count: number;
 }
 
 interface CategoriesPageProps {
   categories: CategoryItem[];
-  onSelect: (category: string) => void;
+  onSelect: (categoryId: string) => void;
 }
 
 export function CategoriesPage({ categories, onSelect }: CategoriesPageProps) {
@@ -17,8 +18,8 @@ export function CategoriesPage({ categories, onSelect }: CategoriesPageProps) {
     <div className="grid gap-4 sm:grid-cols-2 lg:grid-cols-3">
       {categories.map((category) => (
         <button
-          key={category.name}
-          onClick={() => onSelect(category.name)}
+          key={category.id}
+          onClick={() => onSelect(category.id)}
           className="flex items-center gap-4 rounded-2xl border border-zinc-200 bg-white px-4 py-4 text-left shadow-sm transition hover:-translate-y-0.5 hover:shadow-lg active:scale-[0.99] dark:border-zinc-800 dark:bg-zinc-900"
         >
           <span className="flex h-10 w-10 items-center justify-center rounded-xl bg-blue-50 text-blue-600 dark:bg-blue-900/40 dark:text-blue-300">
@@ -1,13 +1,10 @@
 import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { App } from "../types/app.types";
 import { CuratedSection } from "../types/curated.types";
 import { DownloadState } from "../types/download.types";
 import { AppCard } from "../components/AppCard/AppCard";
 
-const CURATED_BASE_URL =
-  "https://raw.githubusercontent.com/Nandanrmenon/fossintosh-repo/main";
-const CURATED_CONFIG_URL = `${CURATED_BASE_URL}/curated.json`;
-
 interface DiscoverPageProps {
   downloadStates: DownloadState;
   onDownload: (appId: string, downloadUrl: string) => void;
@@ -35,53 +32,8 @@ export function DiscoverPage({
         setLoading(true);
         setError("");
 
-        const response = await fetch(CURATED_CONFIG_URL);
-        if (!response.ok) {
-          throw new Error("Failed to fetch curated sections");
-        }
-
-        const payload = (await response.json()) as {
-          sections?: Array<{
-            id?: string;
-            title?: string;
-            description?: string;
-            apps?: string[];
-            appIds?: string[];
-          }>;
-        };
-
-        const sections = payload.sections ?? [];
-        const resolvedSections: CuratedSection[] = [];
-
-        for (const section of sections) {
-          const appIds = section.appIds ?? section.apps ?? [];
-
-          const appPromises = appIds.map(async (appId) => {
-            try {
-              const appResponse = await fetch(
-                `${CURATED_BASE_URL}/apps/${appId}.json`,
-              );
-
-              if (!appResponse.ok) return null;
-              return (await appResponse.json()) as App;
-            } catch (err) {
-              console.error(`Failed to fetch app ${appId}`);
-              console.error(err);
-              return null;
-            }
-          });
-
-          const apps = (await Promise.all(appPromises)).filter(
-            (app): app is App => Boolean(app),
-          );
-
-          resolvedSections.push({
-            id: section.id ?? section.title ?? "untitled-section",
-            title: section.title ?? section.id ?? "Untitled",
-            description: section.description,
-            apps,
-          });
-        }
+        const resolvedSections =
+          await invoke<CuratedSection[]>("fetch_curated");
 
         if (!isMounted) return;
         setCuratedSections(resolvedSections);
@@ -90,7 +42,9 @@ export function DiscoverPage({
         const message =
           err instanceof Error
             ? err.message
-            : "Failed to load curated sections";
+            : typeof err === "string"
+              ? err
+              : "Failed to load curated sections";
         setError(message);
       } finally {
         if (!isMounted) return;
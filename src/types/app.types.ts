@@ -1,3 +1,19 @@
+export interface Artifact {
+  arch: "arm64" | "x86_64" | "universal";
+  url: string;
+  minOsVersion?: string;
+  size?: number;
+  sha256?: string;
+  mirrors?: string[];
+}
+
+export interface Release {
+  version: string;
+  date?: string;
+  releaseNotes?: string;
+  artifacts: Artifact[];
+}
+
 export interface App {
   id: string;
   name: string;
@@ -6,12 +22,37 @@ export interface App {
   category: string;
   icon: string;
   downloadUrl: string;
+  size?: number;
+  sha256?: string;
+  mirrors?: string[];
   homepage: string;
   license: string;
   author: string;
   screenshots: string[];
   installedVersion?: string;
   hasUpdate?: boolean;
+  source?: string;
+  artifacts?: Artifact[];
+  releases?: Release[];
+  selectedArtifact?: Artifact;
+  incompatibleReason?: string;
+}
+
+export interface ManifestFailure {
+  source: string;
+  id: string;
+  url: string;
+  stage: "network" | "http_status" | "parse" | "signature" | "integrity";
+  error: string;
+}
+
+export interface Catalog {
+  apps: App[];
+  stale: boolean;
+  fetchedAt: number;
+  staleSources: string[];
+  failures: ManifestFailure[];
+  sourceErrors: Array<{ source: string; error: string }>;
 }
 
 export interface Category {
@@ -19,6 +60,8 @@ export interface Category {
   name: string;
   icon: string;
   appCount: number;
+  sourceCounts: Record<string, number>;
+  appIds: string[];
 }
 
 export enum InstallStatus {
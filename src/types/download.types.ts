@@ -1,5 +1,8 @@
+import { Artifact } from "./app.types";
+
 export interface DownloadStateEntry {
   isDownloading: boolean;
+  isPaused?: boolean;
   isInstalling: boolean;
   progress: number;
   installProgress: number;
@@ -11,3 +14,40 @@ export interface DownloadStateEntry {
 }
 
 export type DownloadState = Record<string, DownloadStateEntry>;
+
+export type DownloadPhase = "starting" | "downloading" | "verifying" | "paused";
+
+export interface DownloadProgressEvent {
+  app_id: string;
+  progress: number;
+  downloaded: number;
+  total: number;
+  phase: DownloadPhase;
+  bytes_per_second: number;
+  eta_seconds?: number | null;
+}
+
+export type JobState =
+  | "queued"
+  | "active"
+  | "paused"
+  | "completed"
+  | "failed"
+  | "cancelled";
+
+export interface Job {
+  appId: string;
+  artifact: Artifact;
+  priority: number;
+  install: boolean;
+  state: JobState;
+  filePath?: string;
+  sourceUrl?: string;
+  error?: string;
+}
+
+export interface QueueSnapshot {
+  queued: Job[];
+  active: Job[];
+  completed: Job[];
+}